@@ -0,0 +1,63 @@
+pub type PhysPageNum = usize;
+pub type PhyAddr = usize;
+pub type VirPageNum = usize;
+pub type VirAddr = usize;
+
+pub const PA_WIDTH: usize = 56;
+pub const VA_WIDTH: usize = 39;
+pub const PPN_WIDTH: usize = 44;
+pub const VPN_WIDTH: usize = 27;
+pub const PA_OFFSET: usize = 11;
+pub const VA_OFFSET: usize = 9;
+pub const PAGE_SIZE: usize = 1 << 12;
+pub const PAGE_WIDTH: usize = 12;
+pub const MAX_VA: usize = usize::MAX - PAGE_SIZE;
+
+pub fn to_pa(v: usize) -> PhyAddr {
+    v & ((1 << PA_WIDTH )- 1)
+}
+
+pub fn to_va(v: usize) -> VirAddr {
+    v & ((1 << VA_WIDTH) - 1)
+}
+
+pub fn to_ppn(v: usize) -> PhysPageNum {
+    v & ((1 << PPN_WIDTH) - 1)
+}
+
+pub fn to_vpn(v: usize) -> VirPageNum {
+    v & ((1 << VA_WIDTH) - 1)
+}
+
+pub fn addr_to_page_num(v: usize) -> usize {
+    v >> PAGE_WIDTH
+}
+
+pub fn page_num_to_addr(v: usize) -> usize { v << PAGE_WIDTH }
+
+pub fn floor(v: usize) -> usize {
+    v >>PAGE_WIDTH
+}
+
+pub fn ceiling(v: usize) -> usize {
+    if v == 0 {
+        return 0;
+    }
+    ((v - 1) / PAGE_SIZE) + 1
+}
+
+pub fn get_offset(v: usize) -> usize {
+    v & (PAGE_SIZE - 1)
+}
+
+pub fn get_vir_indexes(v: VirPageNum) -> [usize; 3] {
+    [v >> (2 * VA_OFFSET) & ((1 << VA_OFFSET) - 1),
+        v >> VA_OFFSET & ((1 << VA_OFFSET) - 1),
+        v & ((1 << VA_OFFSET) - 1)]
+}
+
+pub fn get_phys_indexes(v: PhysPageNum) -> [usize; 3] {
+    [v & ((1 << PA_OFFSET) - 1),
+        v >> PA_OFFSET & ((1 << PA_OFFSET) - 1),
+        v >> (2 * PA_OFFSET) & ((1 << PA_OFFSET) - 1)]
+}
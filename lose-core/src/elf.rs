@@ -0,0 +1,103 @@
+use alloc::vec::Vec;
+use crate::addr::{page_num_to_addr, VirAddr, VirPageNum};
+use crate::map_area::{MAP_PERM_R, MAP_PERM_U, MAP_PERM_W, MAP_PERM_X, MapArea, MapType};
+
+pub struct ElfSegment<'a> {
+    pub area: MapArea,
+    //file backed part of the segment, the rest of the area is zero filled
+    pub data: &'a [u8],
+}
+
+pub struct ElfImage<'a> {
+    pub entry: usize,
+    pub segments: Vec<ElfSegment<'a>>,
+    pub max_end_vpn: VirPageNum,
+    //where the program headers sit in user memory, 0 if they are not loaded
+    pub phdr: VirAddr,
+    pub phent: usize,
+    pub phnum: usize,
+}
+
+//auxiliary vector keys, as in linux
+pub const AT_NULL: usize = 0;
+pub const AT_PHDR: usize = 3;
+pub const AT_PHENT: usize = 4;
+pub const AT_PHNUM: usize = 5;
+pub const AT_PAGESZ: usize = 6;
+pub const AT_ENTRY: usize = 9;
+pub const AT_RANDOM: usize = 25;
+
+//user areas for every loadable segment of elf_data, not yet mapped into any page table
+pub fn parse_elf<'a>(elf_data: &'a [u8]) -> Result<ElfImage<'a>, &'static str> {
+    let elf = xmas_elf::ElfFile::new(elf_data)?;
+    let elf_header = elf.header;
+    let magic = elf_header.pt1.magic;
+    if magic != [0x7f, 0x45, 0x4c, 0x46] {
+        return Err("invalid elf!");
+    }
+    let mut max_end_vpn: VirPageNum = 0;
+    let mut segments = Vec::new();
+    let ph_offset = elf_header.pt2.ph_offset();
+    let mut phdr: VirAddr = 0;
+    for i in 0..elf_header.pt2.ph_count() {
+        let ph = elf.program_header(i)?;
+        if ph.get_type()? == xmas_elf::program::Type::Phdr {
+            phdr = ph.virtual_addr() as VirAddr;
+        }
+        //without PT_PHDR, find the headers inside a loaded segment
+        if phdr == 0 && ph.get_type()? == xmas_elf::program::Type::Load
+            && ph.offset() <= ph_offset && ph_offset < ph.offset() + ph.file_size() {
+            phdr = (ph.virtual_addr() + ph_offset - ph.offset()) as VirAddr;
+        }
+        if ph.get_type()? == xmas_elf::program::Type::Load {
+            let start_va: VirAddr = ph.virtual_addr() as VirAddr;
+            let end_va: VirAddr = (ph.virtual_addr() + ph.mem_size()) as VirAddr;
+            let mut map_perm = MAP_PERM_U;
+            let ph_flags = ph.flags();
+            if ph_flags.is_read() {
+                map_perm |= MAP_PERM_R;
+            }
+            if ph_flags.is_write() {
+                map_perm |= MAP_PERM_W;
+            }
+            if ph_flags.is_execute() {
+                map_perm |= MAP_PERM_X;
+            }
+            let map_area = MapArea::new(
+                start_va,
+                end_va,
+                MapType::Framed,
+                map_perm
+            );
+            if ph.offset() + ph.file_size() > elf_data.len() as u64 {
+                return Err("segment out of file range");
+            }
+            max_end_vpn = max_end_vpn.max(map_area.end);
+            segments.push(ElfSegment {
+                area: map_area,
+                data: &elf.input[ph.offset() as usize..(ph.offset() + ph.file_size()) as usize],
+            });
+        }
+    }
+    Ok(ElfImage {
+        entry: elf_header.pt2.entry_point() as usize,
+        segments,
+        max_end_vpn,
+        phdr,
+        phent: elf_header.pt2.ph_entry_size() as usize,
+        phnum: elf_header.pt2.ph_count() as usize,
+    })
+}
+
+//the stack hangs from the top of the lower half of sv39, far from the heap
+pub const USER_STACK_TOP: VirAddr = 0x40_0000_0000;
+
+//user stack [bottom, top)
+pub fn user_stack_range(stack_size: usize) -> (VirAddr, VirAddr) {
+    (USER_STACK_TOP - stack_size, USER_STACK_TOP)
+}
+
+//the heap starts empty on the page right above the image and grows with brk
+pub fn heap_base(max_end_vpn: VirPageNum) -> VirAddr {
+    page_num_to_addr(max_end_vpn)
+}
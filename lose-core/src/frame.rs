@@ -0,0 +1,74 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use crate::addr::PhysPageNum;
+
+pub struct FrameAllocator{
+    begin: PhysPageNum,
+    current: PhysPageNum,
+    end: PhysPageNum,
+    recycled: Vec<PhysPageNum>,
+    //how many mappings hold each frame of [begin, end), frames are shared by cow fork
+    ref_counts: Vec<u32>,
+}
+
+impl Default for FrameAllocator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FrameAllocator{
+    pub fn new()->Self{
+        FrameAllocator{
+            begin: 0,
+            current: 0,
+            end: 0,
+            recycled: vec![],
+            ref_counts: vec![],
+        }
+    }
+
+    pub fn init(&mut self,begin:PhysPageNum,end:PhysPageNum){
+        self.begin=begin;
+        self.current=begin;
+        self.end=end;
+        self.ref_counts=vec![0; end - begin];
+    }
+
+    pub fn alloc(&mut self)->Option<PhysPageNum>{
+        let frame = if !self.recycled.is_empty(){
+            self.recycled.pop()
+        }else if self.current==self.end{
+            None
+        }else {
+            self.current+=1;
+            Some(self.current-1)
+        };
+        if let Some(frame) = frame {
+            self.ref_counts[frame - self.begin] = 1;
+        }
+        frame
+    }
+
+    //one more mapping holds frame
+    pub fn share(&mut self,frame:PhysPageNum){
+        let count = &mut self.ref_counts[frame - self.begin];
+        assert!(*count > 0, "sharing free frame {:#x}", frame);
+        *count += 1;
+    }
+
+    pub fn ref_count(&self,frame:PhysPageNum)->usize{
+        self.ref_counts[frame - self.begin] as usize
+    }
+
+    //drops one reference, the frame is recycled once nobody holds it
+    pub fn dealloc(&mut self,recycle:PhysPageNum){
+        let count = &mut self.ref_counts[recycle - self.begin];
+        assert!(*count > 0, "frame {:#x} freed twice", recycle);
+        *count -= 1;
+        if *count == 0 {
+            self.recycled.push(recycle)
+        }
+    }
+
+}
@@ -0,0 +1,12 @@
+//! Hardware independent pieces of the lOSe kernel, buildable and testable on the host
+
+#![no_std]
+
+extern crate alloc;
+
+pub mod addr;
+pub mod buddy;
+pub mod elf;
+pub mod frame;
+pub mod map_area;
+pub mod recycle_counter;
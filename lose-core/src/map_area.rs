@@ -0,0 +1,87 @@
+use alloc::collections::BTreeMap;
+use crate::addr::{ceiling, floor, PhysPageNum, VirAddr, VirPageNum};
+
+#[derive(Copy, Clone)]
+pub enum MapType{
+    Identical,
+    Framed
+}
+
+//identical to PTE flags
+pub const MAP_PERM_R:usize=1<<1;
+pub const MAP_PERM_W:usize=1<<2;
+pub const MAP_PERM_X:usize=1<<3;
+pub const MAP_PERM_U:usize=1<<4;
+
+//prot bits of mmap, as in linux
+pub const PROT_READ:usize=1<<0;
+pub const PROT_WRITE:usize=1<<1;
+pub const PROT_EXEC:usize=1<<2;
+
+//user map_perm for prot, None unless prot is a set of PROT_* bits.
+//PROT_NONE gets no permission at all, every access faults
+pub fn prot_to_perm(prot:usize)->Option<usize>{
+    if prot & !(PROT_READ|PROT_WRITE|PROT_EXEC)!=0 {
+        return None;
+    }
+    if prot==0 {
+        return Some(0);
+    }
+    Some(prot<<1|MAP_PERM_U)
+}
+
+
+#[derive(Clone)]
+pub struct MapArea {
+    pub start: VirPageNum,
+    pub end: VirPageNum,
+    //actual mem [start, end]
+    pub frame_mapping: BTreeMap<VirPageNum,PhysPageNum>,
+    pub map_type:MapType,
+    pub map_perm:usize
+}
+
+impl MapArea {
+    pub fn new (start_va:VirAddr,end_va:VirAddr,map_type:MapType,map_perm:usize)->Self{
+        MapArea {
+            start:floor(start_va) ,
+            end:ceiling(end_va),
+            frame_mapping: BTreeMap::new(),
+            map_type,
+            map_perm
+        }
+    }
+
+    pub fn contains(&self, vpn: VirPageNum) -> bool {
+        self.start <= vpn && vpn < self.end
+    }
+
+    //self keeps [start, at), the returned area takes [at, end) along with its frames
+    pub fn split_off(&mut self, at: VirPageNum) -> MapArea {
+        assert!(self.start <= at && at <= self.end, "split at {:#x} outside the area", at);
+        let upper = MapArea {
+            start: at,
+            end: self.end,
+            frame_mapping: self.frame_mapping.split_off(&at),
+            map_type: self.map_type,
+            map_perm: self.map_perm,
+        };
+        self.end = at;
+        upper
+    }
+
+    //give up the pages from new_end on, unmap gets every backed one
+    pub fn shrink_to(&mut self, new_end: VirPageNum, mut unmap: impl FnMut(VirPageNum, PhysPageNum)) {
+        for (vpn, ppn) in self.frame_mapping.split_off(&new_end) {
+            unmap(vpn, ppn);
+        }
+        self.end = new_end;
+    }
+
+    //hands every mapped frame back through dealloc, which is frame_dealloc in the kernel
+    pub fn recycle(& self, mut dealloc: impl FnMut(PhysPageNum)){
+        for (_,ppn )in self.frame_mapping.iter(){
+            dealloc(*ppn as PhysPageNum);
+        }
+    }
+}
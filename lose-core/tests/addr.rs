@@ -0,0 +1,40 @@
+use lose_core::addr::*;
+
+#[test]
+fn floor_and_ceiling_round_to_pages() {
+    assert_eq!(floor(0), 0);
+    assert_eq!(floor(PAGE_SIZE - 1), 0);
+    assert_eq!(floor(PAGE_SIZE), 1);
+    assert_eq!(ceiling(0), 0);
+    assert_eq!(ceiling(1), 1);
+    assert_eq!(ceiling(PAGE_SIZE), 1);
+    assert_eq!(ceiling(PAGE_SIZE + 1), 2);
+    assert_eq!(ceiling(usize::MAX), usize::MAX / PAGE_SIZE + 1);
+}
+
+#[test]
+fn page_number_round_trip() {
+    for ppn in [0usize, 1, 0x80000, 0xfffffff] {
+        assert_eq!(addr_to_page_num(page_num_to_addr(ppn)), ppn);
+    }
+    assert_eq!(addr_to_page_num(0x8000_0fff), 0x80000);
+    assert_eq!(get_offset(0x8000_0fff), 0xfff);
+    assert_eq!(get_offset(0x8000_1000), 0);
+}
+
+#[test]
+fn width_masks() {
+    assert_eq!(to_va(usize::MAX), (1 << VA_WIDTH) - 1);
+    assert_eq!(to_pa(usize::MAX), (1 << PA_WIDTH) - 1);
+    assert_eq!(to_ppn(usize::MAX), (1 << PPN_WIDTH) - 1);
+    assert_eq!(to_va(0x1234), 0x1234);
+}
+
+#[test]
+fn sv39_indexes_from_high_to_low() {
+    let vpn = (3 << 18) | (5 << 9) | 7;
+    assert_eq!(get_vir_indexes(vpn), [3, 5, 7]);
+    assert_eq!(get_vir_indexes(0), [0, 0, 0]);
+    assert_eq!(get_vir_indexes((1 << VPN_WIDTH) - 1), [511, 511, 511]);
+    assert_eq!(get_vir_indexes(addr_to_page_num(MAX_VA) & ((1 << VPN_WIDTH) - 1)), [511, 511, 510]);
+}
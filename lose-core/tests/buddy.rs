@@ -0,0 +1,175 @@
+use std::alloc::{alloc, dealloc, Layout};
+use lose_core::buddy::{lowbit, prev_power_of_two, BuddyInner};
+
+const ARENA_SIZE: usize = 1 << 16;
+
+//arena aligned to its own size, so it becomes a single top level block
+struct Arena {
+    start: usize,
+    layout: Layout,
+}
+
+impl Arena {
+    fn new() -> Self {
+        let layout = Layout::from_size_align(ARENA_SIZE, ARENA_SIZE).unwrap();
+        let start = unsafe { alloc(layout) } as usize;
+        assert_ne!(start, 0);
+        Arena { start, layout }
+    }
+
+    fn buddy(&self) -> BuddyInner {
+        let mut buddy = BuddyInner::new();
+        unsafe {
+            buddy.init(self.start, ARENA_SIZE);
+        }
+        buddy
+    }
+}
+
+impl Drop for Arena {
+    fn drop(&mut self) {
+        unsafe { dealloc(self.start as *mut u8, self.layout) }
+    }
+}
+
+fn layout(size: usize, align: usize) -> Layout {
+    Layout::from_size_align(size, align).unwrap()
+}
+
+#[test]
+fn power_of_two_helpers() {
+    assert_eq!(prev_power_of_two(1), 1);
+    assert_eq!(prev_power_of_two(5), 4);
+    assert_eq!(prev_power_of_two(4096), 4096);
+    assert_eq!(prev_power_of_two(usize::MAX), 1 << (usize::BITS - 1));
+    assert_eq!(lowbit(12), 4);
+    assert_eq!(lowbit(1 << 20), 1 << 20);
+    assert_eq!(lowbit(0), 0);
+}
+
+#[test]
+fn init_accounts_whole_arena() {
+    let arena = Arena::new();
+    let buddy = arena.buddy();
+    assert_eq!(buddy.size(), ARENA_SIZE);
+    assert_eq!(buddy.occupied(), 0);
+    assert_eq!(buddy.user(), 0);
+}
+
+#[test]
+fn unaligned_range_is_trimmed_to_words() {
+    let arena = Arena::new();
+    let mut buddy = BuddyInner::new();
+    unsafe {
+        buddy.init(arena.start + 3, 100);
+    }
+    //[start + 8, start + 96) after rounding to usize boundaries
+    assert_eq!(buddy.size(), 88);
+}
+
+#[test]
+fn allocations_are_aligned_and_disjoint() {
+    let arena = Arena::new();
+    let mut buddy = arena.buddy();
+    let mut blocks = Vec::new();
+    for (size, align) in [(1, 1), (24, 8), (100, 4), (4096, 4096), (7, 64), (512, 16)] {
+        let ptr = unsafe { buddy.alloc(layout(size, align)) } as usize;
+        assert_ne!(ptr, 0);
+        assert_eq!(ptr % align, 0);
+        assert!(ptr >= arena.start && ptr + size <= arena.start + ARENA_SIZE);
+        blocks.push((ptr, size.next_power_of_two().max(align).max(8)));
+    }
+    for (i, (a, a_size)) in blocks.iter().enumerate() {
+        for (b, b_size) in blocks.iter().skip(i + 1) {
+            assert!(a + a_size <= *b || b + b_size <= *a, "blocks overlap");
+        }
+    }
+    assert_eq!(buddy.user(), 1 + 24 + 100 + 4096 + 7 + 512);
+    assert_eq!(buddy.occupied(), 8 + 32 + 128 + 4096 + 64 + 512);
+}
+
+#[test]
+fn allocated_memory_is_usable() {
+    let arena = Arena::new();
+    let mut buddy = arena.buddy();
+    let a = unsafe { buddy.alloc(layout(256, 8)) };
+    let b = unsafe { buddy.alloc(layout(256, 8)) };
+    unsafe {
+        a.write_bytes(0xaa, 256);
+        b.write_bytes(0x55, 256);
+        assert!(core::slice::from_raw_parts(a, 256).iter().all(|byte| *byte == 0xaa));
+        buddy.dealloc(b as *mut usize, layout(256, 8));
+        let c = buddy.alloc(layout(256, 8));
+        assert_eq!(c, b);
+    }
+}
+
+#[test]
+fn dealloc_coalesces_back_to_one_block() {
+    let arena = Arena::new();
+    let mut buddy = arena.buddy();
+    let small = layout(8, 8);
+    let ptrs: Vec<_> = (0..64).map(|_| unsafe { buddy.alloc(small) }).collect();
+    assert!(ptrs.iter().all(|ptr| !ptr.is_null()));
+    for ptr in ptrs.iter().rev() {
+        unsafe { buddy.dealloc(*ptr as *mut usize, small) };
+    }
+    assert_eq!(buddy.occupied(), 0);
+    assert_eq!(buddy.user(), 0);
+    //only possible if every split block merged back
+    let whole = unsafe { buddy.alloc(layout(ARENA_SIZE, 8)) };
+    assert_eq!(whole as usize, arena.start);
+}
+
+#[test]
+fn dealloc_in_any_order_coalesces() {
+    let arena = Arena::new();
+    let mut buddy = arena.buddy();
+    let block = layout(1024, 8);
+    let ptrs: Vec<_> = (0..ARENA_SIZE / 1024).map(|_| unsafe { buddy.alloc(block) }).collect();
+    assert!(ptrs.iter().all(|ptr| !ptr.is_null()));
+    //free even blocks first, then odd ones, so merges happen late
+    for ptr in ptrs.iter().step_by(2).chain(ptrs.iter().skip(1).step_by(2)) {
+        unsafe { buddy.dealloc(*ptr as *mut usize, block) };
+    }
+    let whole = unsafe { buddy.alloc(layout(ARENA_SIZE, 8)) };
+    assert_eq!(whole as usize, arena.start);
+}
+
+#[test]
+fn exhaustion_returns_null() {
+    let arena = Arena::new();
+    let mut buddy = arena.buddy();
+    let half = layout(ARENA_SIZE / 2, 8);
+    let a = unsafe { buddy.alloc(half) };
+    let b = unsafe { buddy.alloc(half) };
+    assert!(!a.is_null() && !b.is_null());
+    assert!(unsafe { buddy.alloc(layout(8, 8)) }.is_null());
+    unsafe { buddy.dealloc(a as *mut usize, half) };
+    assert!(!unsafe { buddy.alloc(layout(8, 8)) }.is_null());
+}
+
+#[test]
+fn oversized_request_returns_null() {
+    let arena = Arena::new();
+    let mut buddy = arena.buddy();
+    assert!(unsafe { buddy.alloc(layout(ARENA_SIZE + 1, 8)) }.is_null());
+    assert_eq!(buddy.occupied(), 0);
+}
+
+#[test]
+fn zero_sized_allocation_takes_a_word() {
+    let arena = Arena::new();
+    let mut buddy = arena.buddy();
+    let ptr = unsafe { buddy.alloc(layout(0, 1)) };
+    assert!(!ptr.is_null());
+    assert_eq!(buddy.occupied(), 8);
+    unsafe { buddy.dealloc(ptr as *mut usize, layout(0, 1)) };
+    assert_eq!(buddy.occupied(), 0);
+}
+
+#[test]
+fn uninitialized_allocator_is_empty() {
+    let mut buddy = BuddyInner::new();
+    assert!(unsafe { buddy.alloc(layout(8, 8)) }.is_null());
+}
@@ -0,0 +1,133 @@
+use lose_core::addr::PAGE_SIZE;
+use lose_core::elf::{heap_base, parse_elf, user_stack_range, USER_STACK_TOP};
+use lose_core::map_area::{MAP_PERM_R, MAP_PERM_U, MAP_PERM_W, MAP_PERM_X};
+
+const PF_X: u32 = 1;
+const PF_W: u32 = 2;
+const PF_R: u32 = 4;
+const PT_LOAD: u32 = 1;
+const PT_NOTE: u32 = 4;
+const PT_PHDR: u32 = 6;
+
+struct Segment {
+    p_type: u32,
+    flags: u32,
+    vaddr: u64,
+    data: Vec<u8>,
+    mem_size: u64,
+}
+
+//minimal little endian riscv64 executable with the given program headers
+fn build_elf(entry: u64, segments: &[Segment]) -> Vec<u8> {
+    let ph_size = 56;
+    let mut data_offset = 64 + ph_size * segments.len();
+    let mut elf = Vec::new();
+    elf.extend_from_slice(&[0x7f, b'E', b'L', b'F', 2, 1, 1, 0]);
+    elf.extend_from_slice(&[0; 8]);
+    elf.extend_from_slice(&2u16.to_le_bytes());
+    elf.extend_from_slice(&0xf3u16.to_le_bytes());
+    elf.extend_from_slice(&1u32.to_le_bytes());
+    elf.extend_from_slice(&entry.to_le_bytes());
+    elf.extend_from_slice(&64u64.to_le_bytes());
+    elf.extend_from_slice(&0u64.to_le_bytes());
+    elf.extend_from_slice(&0u32.to_le_bytes());
+    elf.extend_from_slice(&64u16.to_le_bytes());
+    elf.extend_from_slice(&(ph_size as u16).to_le_bytes());
+    elf.extend_from_slice(&(segments.len() as u16).to_le_bytes());
+    elf.extend_from_slice(&64u16.to_le_bytes());
+    elf.extend_from_slice(&0u16.to_le_bytes());
+    elf.extend_from_slice(&0u16.to_le_bytes());
+    for segment in segments {
+        elf.extend_from_slice(&segment.p_type.to_le_bytes());
+        elf.extend_from_slice(&segment.flags.to_le_bytes());
+        elf.extend_from_slice(&(data_offset as u64).to_le_bytes());
+        elf.extend_from_slice(&segment.vaddr.to_le_bytes());
+        elf.extend_from_slice(&segment.vaddr.to_le_bytes());
+        elf.extend_from_slice(&(segment.data.len() as u64).to_le_bytes());
+        elf.extend_from_slice(&segment.mem_size.to_le_bytes());
+        elf.extend_from_slice(&(PAGE_SIZE as u64).to_le_bytes());
+        data_offset += segment.data.len();
+    }
+    for segment in segments {
+        elf.extend_from_slice(&segment.data);
+    }
+    elf
+}
+
+fn sample_elf() -> Vec<u8> {
+    build_elf(0x10000, &[
+        Segment { p_type: PT_LOAD, flags: PF_R | PF_X, vaddr: 0x10000, data: vec![0x13; 0x1800], mem_size: 0x1800 },
+        Segment { p_type: PT_NOTE, flags: PF_R, vaddr: 0, data: vec![1, 2, 3, 4], mem_size: 4 },
+        Segment { p_type: PT_LOAD, flags: PF_R | PF_W, vaddr: 0x12000, data: vec![7; 16], mem_size: 0x3000 },
+    ])
+}
+
+#[test]
+fn loadable_segments_become_user_areas() {
+    let elf = sample_elf();
+    let image = parse_elf(&elf).unwrap();
+    assert_eq!(image.entry, 0x10000);
+    assert_eq!(image.segments.len(), 2);
+    let text = &image.segments[0];
+    assert_eq!((text.area.start, text.area.end), (0x10, 0x12));
+    assert_eq!(text.area.map_perm, MAP_PERM_U | MAP_PERM_R | MAP_PERM_X);
+    assert_eq!(text.data.len(), 0x1800);
+    let data = &image.segments[1];
+    assert_eq!((data.area.start, data.area.end), (0x12, 0x15));
+    assert_eq!(data.area.map_perm, MAP_PERM_U | MAP_PERM_R | MAP_PERM_W);
+    //bss part of the segment is not backed by the file
+    assert_eq!(data.data, &[7; 16]);
+    assert_eq!(image.max_end_vpn, 0x15);
+    assert!(image.segments.iter().all(|segment| segment.area.frame_mapping.is_empty()));
+}
+
+#[test]
+fn max_end_does_not_depend_on_segment_order() {
+    let elf = build_elf(0x20000, &[
+        Segment { p_type: PT_LOAD, flags: PF_R | PF_W, vaddr: 0x30000, data: vec![], mem_size: 0x10 },
+        Segment { p_type: PT_LOAD, flags: PF_R | PF_X, vaddr: 0x20000, data: vec![0; 4], mem_size: 4 },
+    ]);
+    assert_eq!(parse_elf(&elf).unwrap().max_end_vpn, 0x31);
+}
+
+#[test]
+fn program_headers_are_located_for_auxv() {
+    let image_elf = sample_elf();
+    let image = parse_elf(&image_elf).unwrap();
+    assert_eq!((image.phent, image.phnum), (56, 3));
+    //headers are not inside any loaded segment of the sample
+    assert_eq!(image.phdr, 0);
+    let elf = build_elf(0x10000, &[
+        Segment { p_type: PT_PHDR, flags: PF_R, vaddr: 0x10040, data: vec![], mem_size: 0x70 },
+        Segment { p_type: PT_LOAD, flags: PF_R | PF_X, vaddr: 0x10000, data: vec![0x13; 0x100], mem_size: 0x100 },
+    ]);
+    assert_eq!(parse_elf(&elf).unwrap().phdr, 0x10040);
+}
+
+#[test]
+fn rejects_bad_magic() {
+    let mut elf = sample_elf();
+    elf[0] = 0;
+    assert!(parse_elf(&elf).is_err());
+}
+
+#[test]
+fn rejects_truncated_file() {
+    assert!(parse_elf(&[0x7f, b'E', b'L', b'F']).is_err());
+    let elf = sample_elf();
+    assert!(parse_elf(&elf[..elf.len() - 8]).is_err());
+}
+
+#[test]
+fn user_stack_hangs_from_the_top() {
+    let (bottom, top) = user_stack_range(0x10000);
+    assert_eq!(top, USER_STACK_TOP);
+    assert_eq!(top - bottom, 0x10000);
+    assert_eq!(bottom % PAGE_SIZE, 0);
+}
+
+#[test]
+fn heap_starts_right_after_the_image() {
+    assert_eq!(heap_base(0x15), 0x15000);
+    assert!(heap_base(0x15) < user_stack_range(0x10000).0);
+}
@@ -0,0 +1,87 @@
+use lose_core::frame::FrameAllocator;
+
+#[test]
+fn allocates_linearly_inside_range() {
+    let mut allocator = FrameAllocator::new();
+    allocator.init(0x80100, 0x80104);
+    let frames: Vec<_> = (0..4).map(|_| allocator.alloc().unwrap()).collect();
+    assert_eq!(frames, vec![0x80100, 0x80101, 0x80102, 0x80103]);
+}
+
+#[test]
+fn exhaustion_returns_none() {
+    let mut allocator = FrameAllocator::new();
+    allocator.init(10, 12);
+    assert!(allocator.alloc().is_some());
+    assert!(allocator.alloc().is_some());
+    assert_eq!(allocator.alloc(), None);
+    assert_eq!(allocator.alloc(), None);
+}
+
+#[test]
+fn recycled_frames_are_reused_before_fresh_ones() {
+    let mut allocator = FrameAllocator::new();
+    allocator.init(0, 100);
+    let a = allocator.alloc().unwrap();
+    let b = allocator.alloc().unwrap();
+    allocator.dealloc(a);
+    allocator.dealloc(b);
+    assert_eq!(allocator.alloc(), Some(b));
+    assert_eq!(allocator.alloc(), Some(a));
+    assert_eq!(allocator.alloc(), Some(2));
+}
+
+#[test]
+fn recycling_refills_an_exhausted_allocator() {
+    let mut allocator = FrameAllocator::new();
+    allocator.init(5, 6);
+    let frame = allocator.alloc().unwrap();
+    assert_eq!(allocator.alloc(), None);
+    allocator.dealloc(frame);
+    assert_eq!(allocator.alloc(), Some(frame));
+}
+
+#[test]
+fn empty_range_allocates_nothing() {
+    let mut allocator = FrameAllocator::new();
+    assert_eq!(allocator.alloc(), None);
+    allocator.init(7, 7);
+    assert_eq!(allocator.alloc(), None);
+}
+
+#[test]
+fn shared_frame_is_recycled_after_last_reference() {
+    let mut allocator = FrameAllocator::new();
+    allocator.init(0, 1);
+    let frame = allocator.alloc().unwrap();
+    assert_eq!(allocator.ref_count(frame), 1);
+    allocator.share(frame);
+    allocator.share(frame);
+    assert_eq!(allocator.ref_count(frame), 3);
+    allocator.dealloc(frame);
+    allocator.dealloc(frame);
+    assert_eq!(allocator.ref_count(frame), 1);
+    assert_eq!(allocator.alloc(), None);
+    allocator.dealloc(frame);
+    assert_eq!(allocator.ref_count(frame), 0);
+    assert_eq!(allocator.alloc(), Some(frame));
+    assert_eq!(allocator.ref_count(frame), 1);
+}
+
+#[test]
+#[should_panic]
+fn double_free_panics() {
+    let mut allocator = FrameAllocator::new();
+    allocator.init(0, 4);
+    let frame = allocator.alloc().unwrap();
+    allocator.dealloc(frame);
+    allocator.dealloc(frame);
+}
+
+#[test]
+#[should_panic]
+fn sharing_a_free_frame_panics() {
+    let mut allocator = FrameAllocator::new();
+    allocator.init(0, 4);
+    allocator.share(2);
+}
@@ -0,0 +1,92 @@
+use lose_core::addr::PAGE_SIZE;
+use lose_core::map_area::{MAP_PERM_R, MAP_PERM_U, MAP_PERM_W, MAP_PERM_X, MapArea, MapType, prot_to_perm, PROT_EXEC, PROT_READ, PROT_WRITE};
+
+#[test]
+fn covers_every_touched_page() {
+    let area = MapArea::new(0x10010, 0x12001, MapType::Framed, MAP_PERM_R);
+    assert_eq!(area.start, 0x10);
+    assert_eq!(area.end, 0x13);
+    let aligned = MapArea::new(0x10000, 0x12000, MapType::Framed, MAP_PERM_R);
+    assert_eq!((aligned.start, aligned.end), (0x10, 0x12));
+}
+
+#[test]
+fn empty_range_has_no_pages() {
+    let area = MapArea::new(3 * PAGE_SIZE, 3 * PAGE_SIZE, MapType::Identical, MAP_PERM_R);
+    assert_eq!(area.start, area.end);
+}
+
+#[test]
+fn recycle_returns_every_mapped_frame() {
+    let mut area = MapArea::new(0, 4 * PAGE_SIZE, MapType::Framed, MAP_PERM_U | MAP_PERM_R | MAP_PERM_W);
+    for vpn in area.start..area.end {
+        area.frame_mapping.insert(vpn, 0x80000 + vpn);
+    }
+    let mut recycled = Vec::new();
+    area.recycle(|ppn| recycled.push(ppn));
+    assert_eq!(recycled, vec![0x80000, 0x80001, 0x80002, 0x80003]);
+}
+
+#[test]
+fn clone_starts_with_the_same_mapping_but_is_independent() {
+    let mut area = MapArea::new(0, 2 * PAGE_SIZE, MapType::Framed, MAP_PERM_R);
+    area.frame_mapping.insert(0, 100);
+    let mut copy = area.clone();
+    copy.frame_mapping.insert(1, 101);
+    assert_eq!(area.frame_mapping.len(), 1);
+    assert_eq!(copy.frame_mapping.len(), 2);
+    assert_eq!(copy.map_perm, MAP_PERM_R);
+}
+
+#[test]
+fn contains_is_half_open() {
+    let area = MapArea::new(0x10000, 0x12000, MapType::Framed, MAP_PERM_R);
+    assert!(!area.contains(0xf));
+    assert!(area.contains(0x10));
+    assert!(area.contains(0x11));
+    assert!(!area.contains(0x12));
+}
+
+#[test]
+fn shrink_unmaps_only_backed_pages_past_the_end() {
+    let mut area = MapArea::new(0, 8 * PAGE_SIZE, MapType::Framed, MAP_PERM_U | MAP_PERM_R | MAP_PERM_W);
+    for vpn in [1, 3, 5, 6] {
+        area.frame_mapping.insert(vpn, 0x80000 + vpn);
+    }
+    let mut unmapped = Vec::new();
+    area.shrink_to(4, |vpn, ppn| unmapped.push((vpn, ppn)));
+    assert_eq!(unmapped, vec![(5, 0x80005), (6, 0x80006)]);
+    assert_eq!(area.end, 4);
+    assert_eq!(area.frame_mapping.keys().copied().collect::<Vec<_>>(), vec![1, 3]);
+    assert!(!area.contains(4));
+}
+
+#[test]
+fn split_off_moves_upper_frames() {
+    let mut area = MapArea::new(0x10000, 0x14000, MapType::Framed, MAP_PERM_U | MAP_PERM_R);
+    area.frame_mapping.insert(0x10, 0x80010);
+    area.frame_mapping.insert(0x12, 0x80012);
+    area.frame_mapping.insert(0x13, 0x80013);
+    let upper = area.split_off(0x12);
+    assert_eq!((area.start, area.end), (0x10, 0x12));
+    assert_eq!((upper.start, upper.end), (0x12, 0x14));
+    assert_eq!(area.frame_mapping.len(), 1);
+    assert_eq!(upper.frame_mapping.keys().copied().collect::<Vec<_>>(), vec![0x12, 0x13]);
+    assert_eq!(upper.map_perm, MAP_PERM_U | MAP_PERM_R);
+}
+
+#[test]
+#[should_panic]
+fn split_off_outside_panics() {
+    let mut area = MapArea::new(0x10000, 0x14000, MapType::Framed, MAP_PERM_R);
+    area.split_off(0x20);
+}
+
+#[test]
+fn prot_maps_onto_user_permissions() {
+    assert_eq!(prot_to_perm(PROT_READ), Some(MAP_PERM_U | MAP_PERM_R));
+    assert_eq!(prot_to_perm(PROT_READ | PROT_WRITE), Some(MAP_PERM_U | MAP_PERM_R | MAP_PERM_W));
+    assert_eq!(prot_to_perm(PROT_READ | PROT_EXEC), Some(MAP_PERM_U | MAP_PERM_R | MAP_PERM_X));
+    assert_eq!(prot_to_perm(0), Some(0));
+    assert_eq!(prot_to_perm(1 << 3), None);
+}
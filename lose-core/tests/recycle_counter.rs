@@ -0,0 +1,39 @@
+use lose_core::recycle_counter::RecycleCounter;
+
+#[test]
+fn hands_out_increasing_ids() {
+    let mut counter = RecycleCounter::new(10);
+    for i in 0..10 {
+        assert_eq!(counter.alloc(), Some(i));
+    }
+}
+
+#[test]
+fn exhausts_at_size() {
+    let mut counter = RecycleCounter::new(2);
+    assert_eq!(counter.alloc(), Some(0));
+    assert_eq!(counter.alloc(), Some(1));
+    assert_eq!(counter.alloc(), None);
+    counter.dealloc(0);
+    assert_eq!(counter.alloc(), Some(0));
+    assert_eq!(counter.alloc(), None);
+}
+
+#[test]
+fn reuses_recycled_ids_first() {
+    let mut counter = RecycleCounter::new(usize::MAX - 1);
+    for _ in 0..5 {
+        counter.alloc();
+    }
+    counter.dealloc(1);
+    counter.dealloc(3);
+    assert_eq!(counter.alloc(), Some(3));
+    assert_eq!(counter.alloc(), Some(1));
+    assert_eq!(counter.alloc(), Some(5));
+}
+
+#[test]
+fn zero_sized_counter_never_allocates() {
+    let mut counter = RecycleCounter::new(0);
+    assert_eq!(counter.alloc(), None);
+}
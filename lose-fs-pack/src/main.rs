@@ -0,0 +1,68 @@
+use std::fs::{read_dir, File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::sync::{Arc, Mutex};
+use lose_fs::{BLOCK_SIZE, BlockDevice, EasyFileSystem};
+
+//16 MiB image
+const TOTAL_BLOCKS: u32 = 16 * 2048;
+const INODE_BITMAP_BLOCKS: u32 = 1;
+
+struct BlockFile(Mutex<File>);
+
+impl BlockDevice for BlockFile {
+    fn read_block(&self, block_id: usize, buf: &mut [u8]) {
+        let mut file = self.0.lock().unwrap();
+        file.seek(SeekFrom::Start((block_id * BLOCK_SIZE) as u64))
+            .expect("Error when seeking!");
+        assert_eq!(file.read(buf).unwrap(), BLOCK_SIZE, "Not a complete block!");
+    }
+
+    fn write_block(&self, block_id: usize, buf: &[u8]) {
+        let mut file = self.0.lock().unwrap();
+        file.seek(SeekFrom::Start((block_id * BLOCK_SIZE) as u64))
+            .expect("Error when seeking!");
+        assert_eq!(file.write(buf).unwrap(), BLOCK_SIZE, "Not a complete block!");
+    }
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() != 4 {
+        eprintln!("usage: {} <app source dir> <app elf dir> <image>", args[0]);
+        std::process::exit(1);
+    }
+    pack(&args[1], &args[2], &args[3]).unwrap();
+}
+
+//every src/bin/<app>.rs becomes a root directory entry holding target/.../release/<app>
+fn pack(src_path: &str, target_path: &str, image_path: &str) -> std::io::Result<()> {
+    let block_file: Arc<dyn BlockDevice> = Arc::new(BlockFile(Mutex::new({
+        let f = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(image_path)?;
+        f.set_len(TOTAL_BLOCKS as u64 * BLOCK_SIZE as u64)?;
+        f
+    })));
+    let efs = EasyFileSystem::create(block_file, TOTAL_BLOCKS, INODE_BITMAP_BLOCKS);
+    let root_inode = EasyFileSystem::root_inode(&efs);
+    let mut apps: Vec<String> = read_dir(src_path)?
+        .map(|dir_entry| {
+            let mut name_with_ext = dir_entry.unwrap().file_name().into_string().unwrap();
+            name_with_ext.drain(name_with_ext.find('.').unwrap()..name_with_ext.len());
+            name_with_ext
+        })
+        .collect();
+    apps.sort();
+    for app in apps {
+        let mut elf = File::open(format!("{}{}", target_path, app))?;
+        let mut all_data: Vec<u8> = Vec::new();
+        elf.read_to_end(&mut all_data)?;
+        let inode = root_inode.create(app.as_str()).expect("Duplicated app name!");
+        assert_eq!(inode.write_at(0, all_data.as_slice()), all_data.len(), "Image is too small!");
+        println!("{}: {} bytes", app, all_data.len());
+    }
+    Ok(())
+}
@@ -0,0 +1,74 @@
+use alloc::sync::Arc;
+use crate::BLOCK_SIZE;
+use crate::block_cache::get_block_cache;
+use crate::block_dev::BlockDevice;
+
+const BLOCK_BITS: usize = BLOCK_SIZE * 8;
+
+type BitmapBlock = [u64; BLOCK_SIZE / 8];
+
+pub struct Bitmap {
+    start_block_id: usize,
+    blocks: usize,
+    //bits past the area the bitmap describes are never handed out
+    maximum: usize,
+}
+
+//bit position -> (block, group of 64 bits, bit in group)
+fn decomposition(mut bit: usize) -> (usize, usize, usize) {
+    let block_pos = bit / BLOCK_BITS;
+    bit %= BLOCK_BITS;
+    (block_pos, bit / 64, bit % 64)
+}
+
+impl Bitmap {
+    pub fn new(start_block_id: usize, blocks: usize, maximum: usize) -> Self {
+        assert!(maximum <= blocks * BLOCK_BITS);
+        Bitmap {
+            start_block_id,
+            blocks,
+            maximum,
+        }
+    }
+
+    pub fn alloc(&self, block_device: &Arc<dyn BlockDevice>) -> Option<usize> {
+        for block_id in 0..self.blocks {
+            let pos = get_block_cache(block_id + self.start_block_id, block_device.clone())
+                .lock()
+                .modify(0, |bitmap_block: &mut BitmapBlock| {
+                    if let Some((bits64_pos, inner_pos)) = bitmap_block
+                        .iter()
+                        .enumerate()
+                        .find(|(_, bits64)| **bits64 != u64::MAX)
+                        .map(|(bits64_pos, bits64)| (bits64_pos, bits64.trailing_ones() as usize)) {
+                        let pos = block_id * BLOCK_BITS + bits64_pos * 64 + inner_pos;
+                        if pos >= self.maximum {
+                            return None;
+                        }
+                        bitmap_block[bits64_pos] |= 1u64 << inner_pos;
+                        Some(pos)
+                    } else {
+                        None
+                    }
+                });
+            if pos.is_some() {
+                return pos;
+            }
+        }
+        None
+    }
+
+    pub fn dealloc(&self, block_device: &Arc<dyn BlockDevice>, bit: usize) {
+        let (block_pos, bits64_pos, inner_pos) = decomposition(bit);
+        get_block_cache(block_pos + self.start_block_id, block_device.clone())
+            .lock()
+            .modify(0, |bitmap_block: &mut BitmapBlock| {
+                assert_ne!(bitmap_block[bits64_pos] & (1u64 << inner_pos), 0, "bit {} is free before dealloc", bit);
+                bitmap_block[bits64_pos] -= 1u64 << inner_pos;
+            });
+    }
+
+    pub fn maximum(&self) -> usize {
+        self.maximum
+    }
+}
@@ -0,0 +1,121 @@
+use alloc::collections::VecDeque;
+use alloc::sync::Arc;
+use lazy_static::lazy_static;
+use spin::Mutex;
+use crate::BLOCK_SIZE;
+use crate::block_dev::BlockDevice;
+
+const BLOCK_CACHE_SIZE: usize = 16;
+
+pub struct BlockCache {
+    cache: [u8; BLOCK_SIZE],
+    block_id: usize,
+    block_device: Arc<dyn BlockDevice>,
+    modified: bool,
+}
+
+impl BlockCache {
+    pub fn new(block_id: usize, block_device: Arc<dyn BlockDevice>) -> Self {
+        let mut cache = [0u8; BLOCK_SIZE];
+        block_device.read_block(block_id, &mut cache);
+        BlockCache {
+            cache,
+            block_id,
+            block_device,
+            modified: false,
+        }
+    }
+
+    fn addr_of_offset(&self, offset: usize) -> usize {
+        &self.cache[offset] as *const _ as usize
+    }
+
+    pub fn get_ref<T: Sized>(&self, offset: usize) -> &T {
+        assert!(offset + core::mem::size_of::<T>() <= BLOCK_SIZE);
+        unsafe { &*(self.addr_of_offset(offset) as *const T) }
+    }
+
+    pub fn get_mut<T: Sized>(&mut self, offset: usize) -> &mut T {
+        assert!(offset + core::mem::size_of::<T>() <= BLOCK_SIZE);
+        self.modified = true;
+        unsafe { &mut *(self.addr_of_offset(offset) as *mut T) }
+    }
+
+    pub fn read<T, V>(&self, offset: usize, f: impl FnOnce(&T) -> V) -> V {
+        f(self.get_ref(offset))
+    }
+
+    pub fn modify<T, V>(&mut self, offset: usize, f: impl FnOnce(&mut T) -> V) -> V {
+        f(self.get_mut(offset))
+    }
+
+    pub fn sync(&mut self) {
+        if self.modified {
+            self.modified = false;
+            self.block_device.write_block(self.block_id, &self.cache);
+        }
+    }
+}
+
+impl Drop for BlockCache {
+    fn drop(&mut self) {
+        self.sync()
+    }
+}
+
+//a block is only the same block on the same device
+fn device_id(block_device: &Arc<dyn BlockDevice>) -> usize {
+    Arc::as_ptr(block_device) as *const () as usize
+}
+
+pub struct BlockCacheManager {
+    //(device, block id, cache)
+    queue: VecDeque<(usize, usize, Arc<Mutex<BlockCache>>)>,
+}
+
+impl BlockCacheManager {
+    pub fn new() -> Self {
+        BlockCacheManager {
+            queue: VecDeque::new(),
+        }
+    }
+
+    pub fn get_block_cache(&mut self, block_id: usize, block_device: Arc<dyn BlockDevice>) -> Arc<Mutex<BlockCache>> {
+        let device = device_id(&block_device);
+        if let Some((_, _, cache)) = self.queue.iter().find(|(dev, id, _)| *dev == device && *id == block_id) {
+            return cache.clone();
+        }
+        if self.queue.len() == BLOCK_CACHE_SIZE {
+            //evict the oldest cache nobody else holds
+            if let Some((idx, _)) = self.queue
+                .iter()
+                .enumerate()
+                .find(|(_, (_, _, cache))| Arc::strong_count(cache) == 1) {
+                self.queue.drain(idx..=idx);
+            } else {
+                panic!("Run out of BlockCache!");
+            }
+        }
+        let block_cache = Arc::new(Mutex::new(BlockCache::new(block_id, block_device)));
+        self.queue.push_back((device, block_id, block_cache.clone()));
+        block_cache
+    }
+
+    pub fn sync_all(&self) {
+        for (_, _, cache) in self.queue.iter() {
+            cache.lock().sync();
+        }
+    }
+}
+
+lazy_static! {
+    pub static ref BLOCK_CACHE_MANAGER: Mutex<BlockCacheManager> = Mutex::new(BlockCacheManager::new());
+}
+
+pub fn get_block_cache(block_id: usize, block_device: Arc<dyn BlockDevice>) -> Arc<Mutex<BlockCache>> {
+    BLOCK_CACHE_MANAGER.lock().get_block_cache(block_id, block_device)
+}
+
+pub fn block_cache_sync_all() {
+    BLOCK_CACHE_MANAGER.lock().sync_all();
+}
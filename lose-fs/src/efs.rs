@@ -0,0 +1,133 @@
+use alloc::sync::Arc;
+use spin::Mutex;
+use crate::BLOCK_SIZE;
+use crate::bitmap::Bitmap;
+use crate::block_cache::{block_cache_sync_all, get_block_cache};
+use crate::block_dev::BlockDevice;
+use crate::layout::{DiskInode, DiskInodeType, SuperBlock};
+use crate::vfs::Inode;
+
+type DataBlock = [u8; BLOCK_SIZE];
+
+const INODE_SIZE: usize = core::mem::size_of::<DiskInode>();
+const INODES_PER_BLOCK: usize = BLOCK_SIZE / INODE_SIZE;
+
+pub struct EasyFileSystem {
+    pub block_device: Arc<dyn BlockDevice>,
+    pub inode_bitmap: Bitmap,
+    pub data_bitmap: Bitmap,
+    inode_area_start_block: u32,
+    data_area_start_block: u32,
+}
+
+impl EasyFileSystem {
+    pub fn create(block_device: Arc<dyn BlockDevice>, total_blocks: u32, inode_bitmap_blocks: u32) -> Arc<Mutex<Self>> {
+        let inode_num = inode_bitmap_blocks as usize * BLOCK_SIZE * 8;
+        let inode_area_blocks = ((inode_num * INODE_SIZE + BLOCK_SIZE - 1) / BLOCK_SIZE) as u32;
+        let inode_total_blocks = inode_bitmap_blocks + inode_area_blocks;
+        assert!(1 + inode_total_blocks < total_blocks, "disk too small for {} inodes", inode_num);
+        let data_total_blocks = total_blocks - 1 - inode_total_blocks;
+        //one bitmap block covers BLOCK_SIZE * 8 data blocks
+        let data_bitmap_blocks = (data_total_blocks + BLOCK_SIZE as u32 * 8) / (BLOCK_SIZE as u32 * 8 + 1);
+        let data_area_blocks = data_total_blocks - data_bitmap_blocks;
+        let inode_bitmap = Bitmap::new(1, inode_bitmap_blocks as usize, inode_num);
+        let data_bitmap = Bitmap::new(
+            (1 + inode_total_blocks) as usize,
+            data_bitmap_blocks as usize,
+            data_area_blocks as usize,
+        );
+        let mut efs = EasyFileSystem {
+            block_device: block_device.clone(),
+            inode_bitmap,
+            data_bitmap,
+            inode_area_start_block: 1 + inode_bitmap_blocks,
+            data_area_start_block: 1 + inode_total_blocks + data_bitmap_blocks,
+        };
+        //clear all blocks
+        for i in 0..total_blocks {
+            get_block_cache(i as usize, block_device.clone())
+                .lock()
+                .modify(0, |data_block: &mut DataBlock| {
+                    data_block.iter_mut().for_each(|byte| *byte = 0);
+                });
+        }
+        get_block_cache(0, block_device.clone())
+            .lock()
+            .modify(0, |super_block: &mut SuperBlock| {
+                super_block.initialize(
+                    total_blocks,
+                    inode_bitmap_blocks,
+                    inode_area_blocks,
+                    data_bitmap_blocks,
+                    data_area_blocks,
+                );
+            });
+        //inode 0 is the root directory
+        assert_eq!(efs.alloc_inode(), Some(0));
+        let (root_inode_block_id, root_inode_offset) = efs.get_disk_inode_pos(0);
+        get_block_cache(root_inode_block_id as usize, block_device.clone())
+            .lock()
+            .modify(root_inode_offset, |disk_inode: &mut DiskInode| {
+                disk_inode.initialize(DiskInodeType::Directory);
+            });
+        block_cache_sync_all();
+        Arc::new(Mutex::new(efs))
+    }
+
+    pub fn open(block_device: Arc<dyn BlockDevice>) -> Arc<Mutex<Self>> {
+        get_block_cache(0, block_device.clone())
+            .lock()
+            .read(0, |super_block: &SuperBlock| {
+                assert!(super_block.is_valid(), "Error loading EFS!");
+                let inode_total_blocks = super_block.inode_bitmap_blocks + super_block.inode_area_blocks;
+                let efs = EasyFileSystem {
+                    block_device: block_device.clone(),
+                    inode_bitmap: Bitmap::new(
+                        1,
+                        super_block.inode_bitmap_blocks as usize,
+                        super_block.inode_area_blocks as usize * INODES_PER_BLOCK,
+                    ),
+                    data_bitmap: Bitmap::new(
+                        (1 + inode_total_blocks) as usize,
+                        super_block.data_bitmap_blocks as usize,
+                        super_block.data_area_blocks as usize,
+                    ),
+                    inode_area_start_block: 1 + super_block.inode_bitmap_blocks,
+                    data_area_start_block: 1 + inode_total_blocks + super_block.data_bitmap_blocks,
+                };
+                Arc::new(Mutex::new(efs))
+            })
+    }
+
+    pub fn root_inode(efs: &Arc<Mutex<Self>>) -> Inode {
+        let block_device = efs.lock().block_device.clone();
+        let (block_id, block_offset) = efs.lock().get_disk_inode_pos(0);
+        Inode::new(block_id, block_offset, efs.clone(), block_device)
+    }
+
+    pub fn get_disk_inode_pos(&self, inode_id: u32) -> (u32, usize) {
+        let block_id = self.inode_area_start_block + inode_id / INODES_PER_BLOCK as u32;
+        (block_id, (inode_id as usize % INODES_PER_BLOCK) * INODE_SIZE)
+    }
+
+    pub fn alloc_inode(&mut self) -> Option<u32> {
+        self.inode_bitmap.alloc(&self.block_device).map(|id| id as u32)
+    }
+
+    pub fn dealloc_inode(&mut self, inode_id: u32) {
+        self.inode_bitmap.dealloc(&self.block_device, inode_id as usize)
+    }
+
+    pub fn alloc_data(&mut self) -> Option<u32> {
+        self.data_bitmap.alloc(&self.block_device).map(|id| id as u32 + self.data_area_start_block)
+    }
+
+    pub fn dealloc_data(&mut self, block_id: u32) {
+        get_block_cache(block_id as usize, self.block_device.clone())
+            .lock()
+            .modify(0, |data_block: &mut DataBlock| {
+                data_block.iter_mut().for_each(|byte| *byte = 0);
+            });
+        self.data_bitmap.dealloc(&self.block_device, (block_id - self.data_area_start_block) as usize)
+    }
+}
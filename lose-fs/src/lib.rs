@@ -0,0 +1,19 @@
+#![no_std]
+//the kernel toolchain predates a stable usize::div_ceil
+#![allow(clippy::manual_div_ceil)]
+
+extern crate alloc;
+
+mod bitmap;
+mod block_cache;
+mod block_dev;
+mod efs;
+mod layout;
+mod vfs;
+
+pub const BLOCK_SIZE: usize = 512;
+
+pub use block_cache::block_cache_sync_all;
+pub use block_dev::BlockDevice;
+pub use efs::EasyFileSystem;
+pub use vfs::Inode;
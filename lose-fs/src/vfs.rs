@@ -0,0 +1,189 @@
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use spin::{Mutex, MutexGuard};
+use crate::block_cache::{block_cache_sync_all, get_block_cache};
+use crate::block_dev::BlockDevice;
+use crate::efs::EasyFileSystem;
+use crate::layout::{DirEntry, DiskInode, DiskInodeType, DIRENT_SIZE, NAME_LENGTH_LIMIT};
+
+//in-memory handle of a disk inode, every operation goes through the block cache
+pub struct Inode {
+    block_id: usize,
+    block_offset: usize,
+    fs: Arc<Mutex<EasyFileSystem>>,
+    block_device: Arc<dyn BlockDevice>,
+}
+
+impl Inode {
+    pub fn new(block_id: u32, block_offset: usize, fs: Arc<Mutex<EasyFileSystem>>, block_device: Arc<dyn BlockDevice>) -> Self {
+        Inode {
+            block_id: block_id as usize,
+            block_offset,
+            fs,
+            block_device,
+        }
+    }
+
+    fn read_disk_inode<V>(&self, f: impl FnOnce(&DiskInode) -> V) -> V {
+        get_block_cache(self.block_id, self.block_device.clone())
+            .lock()
+            .read(self.block_offset, f)
+    }
+
+    fn modify_disk_inode<V>(&self, f: impl FnOnce(&mut DiskInode) -> V) -> V {
+        get_block_cache(self.block_id, self.block_device.clone())
+            .lock()
+            .modify(self.block_offset, f)
+    }
+
+    fn find_inode_id(&self, name: &str, disk_inode: &DiskInode) -> Option<u32> {
+        assert!(disk_inode.is_dir());
+        let file_count = disk_inode.size as usize / DIRENT_SIZE;
+        let mut dirent = DirEntry::empty();
+        for i in 0..file_count {
+            assert_eq!(disk_inode.read_at(DIRENT_SIZE * i, dirent.as_bytes_mut(), &self.block_device), DIRENT_SIZE);
+            if dirent.name() == name {
+                return Some(dirent.inode_number());
+            }
+        }
+        None
+    }
+
+    fn inode_of(&self, fs: &MutexGuard<EasyFileSystem>, inode_id: u32) -> Arc<Inode> {
+        let (block_id, block_offset) = fs.get_disk_inode_pos(inode_id);
+        Arc::new(Inode::new(block_id, block_offset, self.fs.clone(), self.block_device.clone()))
+    }
+
+    pub fn find(&self, name: &str) -> Option<Arc<Inode>> {
+        let fs = self.fs.lock();
+        self.read_disk_inode(|disk_inode| {
+            self.find_inode_id(name, disk_inode)
+                .map(|inode_id| self.inode_of(&fs, inode_id))
+        })
+    }
+
+    fn increase_size(&self, new_size: u32, disk_inode: &mut DiskInode, fs: &mut MutexGuard<EasyFileSystem>) -> bool {
+        if new_size <= disk_inode.size {
+            return true;
+        }
+        let blocks_needed = disk_inode.blocks_num_needed(new_size);
+        let mut new_blocks: Vec<u32> = Vec::new();
+        for _ in 0..blocks_needed {
+            match fs.alloc_data() {
+                Some(block_id) => new_blocks.push(block_id),
+                None => {
+                    //disk full, give back what was taken so far
+                    for block_id in new_blocks {
+                        fs.dealloc_data(block_id);
+                    }
+                    return false;
+                }
+            }
+        }
+        disk_inode.increase_size(new_size, new_blocks, &self.block_device);
+        true
+    }
+
+    pub fn create(&self, name: &str) -> Option<Arc<Inode>> {
+        if name.is_empty() || name.len() > NAME_LENGTH_LIMIT {
+            return None;
+        }
+        let mut fs = self.fs.lock();
+        if self.read_disk_inode(|root_inode| self.find_inode_id(name, root_inode)).is_some() {
+            return None;
+        }
+        let new_inode_id = fs.alloc_inode()?;
+        let (new_inode_block_id, new_inode_block_offset) = fs.get_disk_inode_pos(new_inode_id);
+        get_block_cache(new_inode_block_id as usize, self.block_device.clone())
+            .lock()
+            .modify(new_inode_block_offset, |new_inode: &mut DiskInode| {
+                new_inode.initialize(DiskInodeType::File);
+            });
+        let appended = self.modify_disk_inode(|root_inode| {
+            let file_count = root_inode.size as usize / DIRENT_SIZE;
+            let new_size = (file_count + 1) * DIRENT_SIZE;
+            if !self.increase_size(new_size as u32, root_inode, &mut fs) {
+                return false;
+            }
+            let dirent = DirEntry::new(name, new_inode_id);
+            root_inode.write_at(file_count * DIRENT_SIZE, dirent.as_bytes(), &self.block_device);
+            true
+        });
+        if !appended {
+            fs.dealloc_inode(new_inode_id);
+            return None;
+        }
+        let inode = self.inode_of(&fs, new_inode_id);
+        drop(fs);
+        block_cache_sync_all();
+        Some(inode)
+    }
+
+    pub fn ls(&self) -> Vec<String> {
+        let _fs = self.fs.lock();
+        self.read_disk_inode(|disk_inode| {
+            let file_count = disk_inode.size as usize / DIRENT_SIZE;
+            let mut v: Vec<String> = Vec::new();
+            for i in 0..file_count {
+                let mut dirent = DirEntry::empty();
+                assert_eq!(disk_inode.read_at(i * DIRENT_SIZE, dirent.as_bytes_mut(), &self.block_device), DIRENT_SIZE);
+                v.push(String::from(dirent.name()));
+            }
+            v
+        })
+    }
+
+    pub fn read_at(&self, offset: usize, buf: &mut [u8]) -> usize {
+        let _fs = self.fs.lock();
+        self.read_disk_inode(|disk_inode| disk_inode.read_at(offset, buf, &self.block_device))
+    }
+
+    //returns 0 without writing when the disk cannot hold offset + buf.len() bytes
+    pub fn write_at(&self, offset: usize, buf: &[u8]) -> usize {
+        let mut fs = self.fs.lock();
+        let size = self.modify_disk_inode(|disk_inode| {
+            if !self.increase_size((offset + buf.len()) as u32, disk_inode, &mut fs) {
+                return 0;
+            }
+            disk_inode.write_at(offset, buf, &self.block_device)
+        });
+        drop(fs);
+        block_cache_sync_all();
+        size
+    }
+
+    pub fn read_all(&self) -> Vec<u8> {
+        let mut buffer = [0u8; 512];
+        let mut v: Vec<u8> = Vec::new();
+        loop {
+            let len = self.read_at(v.len(), &mut buffer);
+            if len == 0 {
+                break;
+            }
+            v.extend_from_slice(&buffer[..len]);
+        }
+        v
+    }
+
+    pub fn clear(&self) {
+        let mut fs = self.fs.lock();
+        self.modify_disk_inode(|disk_inode| {
+            for data_block in disk_inode.clear_size(&self.block_device) {
+                fs.dealloc_data(data_block);
+            }
+        });
+        drop(fs);
+        block_cache_sync_all();
+    }
+
+    pub fn size(&self) -> usize {
+        let _fs = self.fs.lock();
+        self.read_disk_inode(|disk_inode| disk_inode.size as usize)
+    }
+
+    pub fn is_dir(&self) -> bool {
+        let _fs = self.fs.lock();
+        self.read_disk_inode(|disk_inode| disk_inode.is_dir())
+    }
+}
@@ -0,0 +1,112 @@
+use std::sync::{Arc, Mutex};
+use lose_fs::{BlockDevice, EasyFileSystem, Inode, BLOCK_SIZE};
+
+//a disk in memory, blocks start zeroed
+struct MemDisk(Mutex<Vec<[u8; BLOCK_SIZE]>>);
+
+impl BlockDevice for MemDisk {
+    fn read_block(&self, block_id: usize, buf: &mut [u8]) {
+        buf.copy_from_slice(&self.0.lock().unwrap()[block_id]);
+    }
+
+    fn write_block(&self, block_id: usize, buf: &[u8]) {
+        self.0.lock().unwrap()[block_id].copy_from_slice(buf);
+    }
+}
+
+fn mem_disk(blocks: usize) -> Arc<dyn BlockDevice> {
+    Arc::new(MemDisk(Mutex::new(vec![[0; BLOCK_SIZE]; blocks])))
+}
+
+fn root(blocks: u32) -> (Arc<dyn BlockDevice>, Inode) {
+    let disk = mem_disk(blocks as usize);
+    let efs = EasyFileSystem::create(disk.clone(), blocks, 1);
+    (disk, EasyFileSystem::root_inode(&efs))
+}
+
+#[test]
+fn create_write_read() {
+    let (_disk, root) = root(4096);
+    assert!(root.is_dir());
+    let file = root.create("hello").unwrap();
+    assert!(!file.is_dir());
+    assert_eq!(file.size(), 0);
+    assert_eq!(file.write_at(0, b"hello, world"), 12);
+    assert_eq!(file.write_at(7, b"lOSe!"), 5);
+    assert_eq!(file.size(), 12);
+    let mut buf = [0u8; 32];
+    assert_eq!(file.read_at(0, &mut buf), 12);
+    assert_eq!(&buf[..12], b"hello, lOSe!");
+    assert_eq!(file.read_at(12, &mut buf), 0);
+    //past the direct blocks into the indirect ones
+    let big: Vec<u8> = (0..64 * BLOCK_SIZE).map(|i| i as u8).collect();
+    assert_eq!(file.write_at(0, &big), big.len());
+    assert_eq!(file.read_all(), big);
+    file.clear();
+    assert_eq!(file.size(), 0);
+    assert!(file.read_all().is_empty());
+}
+
+#[test]
+fn find_and_ls() {
+    let (_disk, root) = root(4096);
+    assert!(root.ls().is_empty());
+    root.create("a").unwrap();
+    root.create("b").unwrap();
+    assert_eq!(root.ls(), ["a", "b"]);
+    assert!(root.create("a").is_none());
+    assert!(root.create("").is_none());
+    assert!(root.create(&"x".repeat(28)).is_none());
+    root.find("b").unwrap().write_at(0, b"bee");
+    assert_eq!(root.find("b").unwrap().read_all(), b"bee");
+    assert!(root.find("c").is_none());
+}
+
+#[test]
+fn reopen_keeps_files() {
+    let (disk, root) = root(4096);
+    root.create("kept").unwrap().write_at(0, b"on disk");
+    let efs = EasyFileSystem::open(disk);
+    let root = EasyFileSystem::root_inode(&efs);
+    assert_eq!(root.ls(), ["kept"]);
+    assert_eq!(root.find("kept").unwrap().read_all(), b"on disk");
+}
+
+#[test]
+fn inodes_run_out() {
+    let (_disk, root) = root(4096);
+    //one bitmap block, the root takes the first inode
+    let inodes = BLOCK_SIZE * 8;
+    for i in 1..inodes {
+        assert!(root.create(&format!("{}", i)).is_some(), "create {}", i);
+    }
+    assert!(root.create("one too many").is_none());
+    assert_eq!(root.ls().len(), inodes - 1);
+}
+
+#[test]
+fn full_disk_frees_inode_of_failed_create() {
+    //the inode area alone takes 1024 blocks
+    let (_disk, root) = root(1200);
+    let filler = root.create("filler").unwrap();
+    //grab data blocks until no file can grow
+    let mut size = 0;
+    while filler.write_at(size, &[1; BLOCK_SIZE]) == BLOCK_SIZE {
+        size += BLOCK_SIZE;
+    }
+    assert_eq!(filler.write_at(size, &[1; BLOCK_SIZE]), 0);
+    assert_eq!(filler.size(), size);
+    //fill up the root directory's last block
+    let mut files = 1;
+    while root.create(&format!("{}", files)).is_some() {
+        files += 1;
+    }
+    //the directory cannot grow, every inode taken for it goes back
+    assert_eq!(root.ls().len(), files);
+    for _ in 0..BLOCK_SIZE * 8 {
+        assert!(root.create("late").is_none());
+    }
+    filler.clear();
+    assert!(root.create("late").is_some());
+    assert_eq!(root.ls().len(), files + 1);
+}
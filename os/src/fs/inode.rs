@@ -0,0 +1,125 @@
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use lazy_static::lazy_static;
+use lose_fs::{EasyFileSystem, Inode};
+use crate::fs::{File, O_CREATE, O_RDWR, O_TRUNC, O_WRONLY, Stat, STAT_MODE_FILE, UserBuffer};
+use crate::io::virtio_blk::BLOCK_DEVICE;
+use crate::println;
+use crate::sync::cell::Mutex;
+use crate::syscall::errno::Errno;
+
+pub struct OSInode {
+    readable: bool,
+    writable: bool,
+    inner: Mutex<OSInodeInner>,
+}
+
+pub struct OSInodeInner {
+    offset: usize,
+    inode: Arc<Inode>,
+}
+
+impl OSInode {
+    pub fn new(readable: bool, writable: bool, inode: Arc<Inode>) -> Self {
+        OSInode {
+            readable,
+            writable,
+            inner: Mutex::new(OSInodeInner { offset: 0, inode }),
+        }
+    }
+
+    pub fn read_all(&self) -> Vec<u8> {
+        let mut inner = self.inner.lock();
+        let data = inner.inode.read_all();
+        inner.offset = data.len();
+        data
+    }
+}
+
+lazy_static! {
+    pub static ref ROOT_INODE: Arc<Inode> = {
+        let efs = EasyFileSystem::open(BLOCK_DEVICE.clone());
+        Arc::new(EasyFileSystem::root_inode(&efs))
+    };
+}
+
+pub fn list_apps() {
+    println!("/**** APPS ****");
+    for app in ROOT_INODE.ls() {
+        println!("{}", app);
+    }
+    println!("**************/");
+}
+
+//(readable, writable) of the access mode in flags
+fn read_write(flags: u32) -> (bool, bool) {
+    if flags & O_WRONLY != 0 {
+        (false, true)
+    } else if flags & O_RDWR != 0 {
+        (true, true)
+    } else {
+        (true, false)
+    }
+}
+
+//the root directory is flat, so a leading '/' is all the path there is
+pub fn open_file(path: &str, flags: u32) -> Option<Arc<OSInode>> {
+    let name = path.trim_start_matches('/');
+    let (readable, writable) = read_write(flags);
+    let inode = if let Some(inode) = ROOT_INODE.find(name) {
+        if flags & O_TRUNC != 0 {
+            inode.clear();
+        }
+        inode
+    } else if flags & O_CREATE != 0 {
+        ROOT_INODE.create(name)?
+    } else {
+        return None;
+    };
+    if inode.is_dir() {
+        return None;
+    }
+    Some(Arc::new(OSInode::new(readable, writable, inode)))
+}
+
+impl File for OSInode {
+    fn readable(&self) -> bool {
+        self.readable
+    }
+
+    fn writable(&self) -> bool {
+        self.writable
+    }
+
+    fn read(&self, buf: UserBuffer) -> Result<usize, Errno> {
+        let mut inner = self.inner.lock();
+        let mut total_read_size = 0usize;
+        for slice in buf.buffers {
+            let read_size = inner.inode.read_at(inner.offset, slice);
+            if read_size == 0 {
+                break;
+            }
+            inner.offset += read_size;
+            total_read_size += read_size;
+        }
+        Ok(total_read_size)
+    }
+
+    fn write(&self, buf: UserBuffer) -> Result<usize, Errno> {
+        let mut inner = self.inner.lock();
+        let mut total_write_size = 0usize;
+        for slice in buf.buffers {
+            let write_size = inner.inode.write_at(inner.offset, slice);
+            inner.offset += write_size;
+            total_write_size += write_size;
+            if write_size < slice.len() {
+                break;
+            }
+        }
+        Ok(total_write_size)
+    }
+
+    fn stat(&self) -> Stat {
+        Stat { mode: STAT_MODE_FILE, size: self.inner.lock().inode.size() }
+    }
+}
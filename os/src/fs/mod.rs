@@ -0,0 +1,72 @@
+use alloc::vec::Vec;
+use crate::mm::{addr_to_page_num, PhysPageNum};
+use crate::mm::frame_allocator::{frame_dealloc, frame_share};
+use crate::syscall::errno::Errno;
+
+pub mod inode;
+pub mod pipe;
+pub mod stdio;
+
+pub const O_RDONLY: u32 = 0;
+pub const O_WRONLY: u32 = 1 << 0;
+pub const O_RDWR: u32 = 1 << 1;
+pub const O_CREATE: u32 = 1 << 9;
+pub const O_TRUNC: u32 = 1 << 10;
+
+pub const STAT_MODE_FILE: u32 = 1 << 15;
+pub const STAT_MODE_CHAR: u32 = 1 << 13;
+pub const STAT_MODE_FIFO: u32 = 1 << 12;
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct Stat {
+    pub mode: u32,
+    pub size: usize,
+}
+
+//user memory of a read/write request, split at page boundaries
+pub struct UserBuffer {
+    pub buffers: Vec<&'static mut [u8]>,
+    _pinned: PinnedFrames,
+}
+
+impl UserBuffer {
+    //call it under the process lock, before another thread can unmap the pages
+    pub fn new(buffers: Vec<&'static mut [u8]>) -> Self {
+        let pinned = PinnedFrames::new(buffers.iter().map(|buffer| addr_to_page_num(buffer.as_ptr() as usize)).collect());
+        UserBuffer { buffers, _pinned: pinned }
+    }
+
+    pub fn len(&self) -> usize {
+        self.buffers.iter().map(|buffer| buffer.len()).sum()
+    }
+}
+
+//files block and copy without the process lock, so the frames under a
+//UserBuffer hold a reference of their own until the request is done
+struct PinnedFrames(Vec<PhysPageNum>);
+
+impl PinnedFrames {
+    fn new(ppns: Vec<PhysPageNum>) -> Self {
+        for ppn in ppns.iter() {
+            frame_share(*ppn);
+        }
+        PinnedFrames(ppns)
+    }
+}
+
+impl Drop for PinnedFrames {
+    fn drop(&mut self) {
+        for ppn in self.0.iter() {
+            frame_dealloc(*ppn);
+        }
+    }
+}
+
+pub trait File: Send + Sync {
+    fn readable(&self) -> bool;
+    fn writable(&self) -> bool;
+    fn read(&self, buf: UserBuffer) -> Result<usize, Errno>;
+    fn write(&self, buf: UserBuffer) -> Result<usize, Errno>;
+    fn stat(&self) -> Stat;
+}
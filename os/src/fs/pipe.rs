@@ -0,0 +1,209 @@
+use alloc::sync::{Arc, Weak};
+use crate::fs::{File, Stat, STAT_MODE_FIFO, UserBuffer};
+use crate::process::wait_queue::WaitQueue;
+use crate::sync::cell::Mutex;
+use crate::syscall::errno::Errno;
+
+const RING_BUFFER_SIZE: usize = 32;
+
+#[derive(Copy, Clone, PartialEq)]
+enum RingBufferStatus {
+    Full,
+    Empty,
+    Normal,
+}
+
+pub struct PipeRingBuffer {
+    arr: [u8; RING_BUFFER_SIZE],
+    head: usize,
+    tail: usize,
+    status: RingBufferStatus,
+    read_end: Option<Weak<Pipe>>,
+    write_end: Option<Weak<Pipe>>,
+}
+
+impl PipeRingBuffer {
+    pub fn new() -> Self {
+        PipeRingBuffer {
+            arr: [0; RING_BUFFER_SIZE],
+            head: 0,
+            tail: 0,
+            status: RingBufferStatus::Empty,
+            read_end: None,
+            write_end: None,
+        }
+    }
+
+    fn set_ends(&mut self, read_end: &Arc<Pipe>, write_end: &Arc<Pipe>) {
+        self.read_end = Some(Arc::downgrade(read_end));
+        self.write_end = Some(Arc::downgrade(write_end));
+    }
+
+    fn read_byte(&mut self) -> u8 {
+        self.status = RingBufferStatus::Normal;
+        let c = self.arr[self.head];
+        self.head = (self.head + 1) % RING_BUFFER_SIZE;
+        if self.head == self.tail {
+            self.status = RingBufferStatus::Empty;
+        }
+        c
+    }
+
+    fn write_byte(&mut self, byte: u8) {
+        self.status = RingBufferStatus::Normal;
+        self.arr[self.tail] = byte;
+        self.tail = (self.tail + 1) % RING_BUFFER_SIZE;
+        if self.tail == self.head {
+            self.status = RingBufferStatus::Full;
+        }
+    }
+
+    fn available_read(&self) -> usize {
+        if self.status == RingBufferStatus::Empty {
+            0
+        } else if self.tail > self.head {
+            self.tail - self.head
+        } else {
+            self.tail + RING_BUFFER_SIZE - self.head
+        }
+    }
+
+    fn available_write(&self) -> usize {
+        if self.status == RingBufferStatus::Full {
+            0
+        } else {
+            RING_BUFFER_SIZE - self.available_read()
+        }
+    }
+
+    //an end is shared by dup and fork, so it is gone only when every copy is dropped
+    fn all_read_ends_closed(&self) -> bool {
+        self.read_end.as_ref().unwrap().upgrade().is_none()
+    }
+
+    fn all_write_ends_closed(&self) -> bool {
+        self.write_end.as_ref().unwrap().upgrade().is_none()
+    }
+}
+
+//both ends share the buffer and the queues of threads waiting on it
+pub struct Pipe {
+    readable: bool,
+    writable: bool,
+    buffer: Arc<Mutex<PipeRingBuffer>>,
+    //readers waiting for data or for the write end to close
+    read_wait: Arc<WaitQueue>,
+    //writers waiting for room or for the read end to close
+    write_wait: Arc<WaitQueue>,
+}
+
+impl Pipe {
+    fn end(readable: bool, buffer: &Arc<Mutex<PipeRingBuffer>>, read_wait: &Arc<WaitQueue>, write_wait: &Arc<WaitQueue>) -> Self {
+        Pipe {
+            readable,
+            writable: !readable,
+            buffer: buffer.clone(),
+            read_wait: read_wait.clone(),
+            write_wait: write_wait.clone(),
+        }
+    }
+}
+
+//the last copy of an end is gone, whoever waits on the other end has to recheck.
+//Under the buffer lock, so a thread that just saw the end open is queued already
+impl Drop for Pipe {
+    fn drop(&mut self) {
+        let _ring_buffer = self.buffer.lock();
+        if self.writable {
+            self.read_wait.wake_all();
+        } else {
+            self.write_wait.wake_all();
+        }
+    }
+}
+
+//returns (read end, write end)
+pub fn make_pipe() -> (Arc<Pipe>, Arc<Pipe>) {
+    let buffer = Arc::new(Mutex::new(PipeRingBuffer::new()));
+    let read_wait = Arc::new(WaitQueue::new());
+    let write_wait = Arc::new(WaitQueue::new());
+    let read_end = Arc::new(Pipe::end(true, &buffer, &read_wait, &write_wait));
+    let write_end = Arc::new(Pipe::end(false, &buffer, &read_wait, &write_wait));
+    buffer.lock().set_ends(&read_end, &write_end);
+    (read_end, write_end)
+}
+
+impl File for Pipe {
+    fn readable(&self) -> bool {
+        self.readable
+    }
+
+    fn writable(&self) -> bool {
+        self.writable
+    }
+
+    fn read(&self, buf: UserBuffer) -> Result<usize, Errno> {
+        assert!(self.readable());
+        let want_to_read = buf.len();
+        if want_to_read == 0 {
+            return Ok(0);
+        }
+        let mut bytes = buf.buffers.into_iter().flat_map(|buffer| buffer.iter_mut());
+        loop {
+            let mut ring_buffer = self.buffer.lock();
+            let loop_read = ring_buffer.available_read();
+            if loop_read == 0 {
+                if ring_buffer.all_write_ends_closed() {
+                    return Ok(0);
+                }
+                if !self.read_wait.wait_then(|| drop(ring_buffer)) {
+                    return Ok(0);
+                }
+                continue;
+            }
+            //return what is available instead of waiting to fill the whole buffer
+            let len = loop_read.min(want_to_read);
+            for byte in bytes.by_ref().take(len) {
+                *byte = ring_buffer.read_byte();
+            }
+            self.write_wait.wake_all();
+            return Ok(len);
+        }
+    }
+
+    //with no reader left the bytes could never be read, EPIPE unless some already went in
+    fn write(&self, buf: UserBuffer) -> Result<usize, Errno> {
+        assert!(self.writable());
+        let want_to_write = buf.len();
+        if want_to_write == 0 {
+            return Ok(0);
+        }
+        let mut bytes = buf.buffers.into_iter().flat_map(|buffer| buffer.iter_mut());
+        let mut already_write = 0usize;
+        loop {
+            let mut ring_buffer = self.buffer.lock();
+            let loop_write = ring_buffer.available_write();
+            if ring_buffer.all_read_ends_closed() {
+                return if already_write > 0 { Ok(already_write) } else { Err(Errno::EPIPE) };
+            }
+            if loop_write == 0 {
+                if !self.write_wait.wait_then(|| drop(ring_buffer)) {
+                    return Ok(already_write);
+                }
+                continue;
+            }
+            for byte in bytes.by_ref().take(loop_write) {
+                ring_buffer.write_byte(*byte);
+                already_write += 1;
+            }
+            self.read_wait.wake_all();
+            if already_write == want_to_write {
+                return Ok(already_write);
+            }
+        }
+    }
+
+    fn stat(&self) -> Stat {
+        Stat { mode: STAT_MODE_FIFO, size: self.buffer.lock().available_read() }
+    }
+}
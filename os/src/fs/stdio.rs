@@ -0,0 +1,70 @@
+use crate::fs::{File, Stat, STAT_MODE_CHAR, UserBuffer};
+use crate::io::uart::{uart_getchar, uart_putchar};
+use crate::syscall::errno::Errno;
+
+pub struct Stdin;
+
+pub struct Stdout;
+
+impl File for Stdin {
+    fn readable(&self) -> bool {
+        true
+    }
+
+    fn writable(&self) -> bool {
+        false
+    }
+
+    fn read(&self, mut buf: UserBuffer) -> Result<usize, Errno> {
+        if buf.len() == 0 {
+            return Ok(0);
+        }
+        let ch = match unsafe { uart_getchar() } {
+            Some(ch) => ch,
+            None => return Ok(0),
+        };
+        unsafe {
+            buf.buffers[0].as_mut_ptr().write_volatile(ch);
+        }
+        Ok(1)
+    }
+
+    fn write(&self, _buf: UserBuffer) -> Result<usize, Errno> {
+        panic!("Cannot write to stdin!");
+    }
+
+    fn stat(&self) -> Stat {
+        Stat { mode: STAT_MODE_CHAR, size: 0 }
+    }
+}
+
+impl File for Stdout {
+    fn readable(&self) -> bool {
+        false
+    }
+
+    fn writable(&self) -> bool {
+        true
+    }
+
+    fn read(&self, _buf: UserBuffer) -> Result<usize, Errno> {
+        panic!("Cannot read from stdout!");
+    }
+
+    fn write(&self, buf: UserBuffer) -> Result<usize, Errno> {
+        let mut written = 0;
+        for buffer in buf.buffers {
+            for c in buffer.iter() {
+                if !unsafe { uart_putchar(*c) } {
+                    return Ok(written);
+                }
+                written += 1;
+            }
+        }
+        Ok(written)
+    }
+
+    fn stat(&self) -> Stat {
+        Stat { mode: STAT_MODE_CHAR, size: 0 }
+    }
+}
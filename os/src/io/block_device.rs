@@ -0,0 +1 @@
+pub use lose_fs::{BLOCK_SIZE, BlockDevice};
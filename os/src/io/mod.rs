@@ -1,23 +1,50 @@
 pub mod uart;
+pub mod plic;
+pub mod block_device;
+pub mod virtio_blk;
 
 use core::fmt;
 use core::fmt::Write;
-use crate::io::uart::{uart_init, uart_putchar, uart_work};
+use lazy_static::initialize;
+use crate::io::uart::{uart_init, uart_intr, uart_putchar_sync, UART0_IRQ};
+use crate::io::virtio_blk::BLOCK_DEVICE;
+use crate::sync::cell::Mutex;
 
 struct STDOUT;
 
+//keeps lines printed by different harts apart
+static PRINT_LOCK: Mutex<()> = Mutex::new(());
+
 pub fn init() {
     unsafe {
         uart_init();
     }
+    plic::init();
+    plic::init_hart();
+}
+
+//handle one device interrupt claimed from the plic. Only the boot hart
+//enables them in init, so the others never get here
+pub fn external_interrupt() {
+    let irq = plic::claim();
+    match irq {
+        0 => return,
+        UART0_IRQ => unsafe { uart_intr() },
+        _ => println!("[kernel] Unexpected interrupt irq = {}", irq),
+    }
+    plic::complete(irq);
+}
+
+//the disk driver takes its queues from the frame allocator, so call this after mm::init
+pub fn block_init() {
+    initialize(&BLOCK_DEVICE);
 }
 
 impl Write for STDOUT {
     fn write_str(&mut self, s: &str) -> core::fmt::Result {
-        for c in s.chars() {
+        for c in s.bytes() {
             unsafe {
-                uart_putchar(c as u8);
-                uart_work();
+                uart_putchar_sync(c);
             }
         }
         Ok(())
@@ -25,14 +52,14 @@ impl Write for STDOUT {
 
     fn write_char(&mut self, c: char) -> core::fmt::Result {
         unsafe {
-            uart_putchar(c as u8);
-            uart_work();
+            uart_putchar_sync(c as u8);
         }
         Ok(())
     }
 }
 
 pub fn print(args: fmt::Arguments) {
+    let _guard = PRINT_LOCK.lock();
     STDOUT.write_fmt(args).unwrap();
 }
 
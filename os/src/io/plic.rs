@@ -0,0 +1,44 @@
+use crate::io::uart::UART0_IRQ;
+use crate::utility::hart_id;
+
+pub const PLIC: usize = 0x0c000000;
+const PLIC_PRIORITY: usize = PLIC;
+
+//registers of the supervisor mode context of a hart
+fn plic_senable(hart: usize) -> usize {
+    PLIC + 0x2080 + hart * 0x100
+}
+
+fn plic_spriority(hart: usize) -> usize {
+    PLIC + 0x201000 + hart * 0x2000
+}
+
+fn plic_sclaim(hart: usize) -> usize {
+    PLIC + 0x201004 + hart * 0x2000
+}
+
+//nonzero priority lets the irq through at all
+pub fn init() {
+    unsafe {
+        ((PLIC_PRIORITY + UART0_IRQ as usize * 4) as *mut u32).write_volatile(1);
+    }
+}
+
+pub fn init_hart() {
+    let hart = hart_id();
+    unsafe {
+        (plic_senable(hart) as *mut u32).write_volatile(1 << UART0_IRQ);
+        (plic_spriority(hart) as *mut u32).write_volatile(0);
+    }
+}
+
+//the highest priority pending irq, 0 if none
+pub fn claim() -> u32 {
+    unsafe { (plic_sclaim(hart_id()) as *const u32).read_volatile() }
+}
+
+pub fn complete(irq: u32) {
+    unsafe {
+        (plic_sclaim(hart_id()) as *mut u32).write_volatile(irq);
+    }
+}
@@ -2,10 +2,13 @@
 #![no_main]
 
 use core::sync::atomic::AtomicU64;
+use lazy_static::lazy_static;
 use crate::io::STDOUT;
+use crate::process::wait_queue::WaitQueue;
+use crate::sync::cell::Mutex;
 
 pub const UART0: u64 = 0x10000000;
-const UART0_IRQ: u32 = 10;
+pub const UART0_IRQ: u32 = 10;
 const RHR: u64 = 0;
 const THR: u64 = 0;
 const IER: u64 = 1;
@@ -19,17 +22,17 @@ const LCR: u64 = 3;
 const LCR_EIGHT_BITS: u8 = 3 << 0;
 const LCR_BAUD_LATCH: u8 = 1 << 7;
 const LSR: u64 = 5;
-const LSR_TX_READY: u8 = 1 << 0;
+const LSR_RX_READY: u8 = 1 << 0;
 const LSR_TX_IDLE: u8 = 1 << 5;
 
 unsafe fn read_reg(reg: u64) -> u8 {
     let ptr = (UART0 + reg) as * mut u8;
-    return *ptr;
+    return ptr.read_volatile();
 }
 
 unsafe fn write_reg(reg: u64,ch: u8) {
     let ptr = (UART0 + reg) as *mut u8;
-    *ptr = ch;
+    ptr.write_volatile(ch);
 }
 
 const UART_TX_BUF_SIZE: u64 = 32;
@@ -38,6 +41,22 @@ static mut uart_tx_buf: [u64; UART_TX_BUF_SIZE as usize] = [0; UART_TX_BUF_SIZE
 static mut uart_tx_w: u64=0 ;
 static mut uart_tx_r: u64=0 ;
 
+//console input, filled by the interrupt handler
+const UART_RX_BUF_SIZE: u64 = 128;
+
+static mut uart_rx_buf: [u8; UART_RX_BUF_SIZE as usize] = [0; UART_RX_BUF_SIZE as usize];
+static mut uart_rx_w: u64=0 ;
+static mut uart_rx_r: u64=0 ;
+
+//taken around every use of the buffers below, which any hart may touch
+static UART_LOCK: Mutex<()> = Mutex::new(());
+
+lazy_static! {
+    //readers waiting for input
+    static ref UART_RX_WAIT: WaitQueue = WaitQueue::new();
+    //writers waiting for room in uart_tx_buf
+    static ref UART_TX_WAIT: WaitQueue = WaitQueue::new();
+}
 
 pub unsafe fn uart_init() {
     write_reg(IER, 0x00);
@@ -49,21 +68,48 @@ pub unsafe fn uart_init() {
     write_reg(IER, IER_TX_ENABLE | IER_RX_ENABLE);
 }
 
-pub unsafe  fn uart_putchar(c: u8) {
-    while uart_tx_w == uart_tx_r + UART_TX_BUF_SIZE {}
-    uart_tx_buf[(uart_tx_w % UART_TX_BUF_SIZE) as usize] = c as u64;
-    uart_tx_w += 1;
-    uart_work();
+//for user writes, blocks while uart_tx_buf is full and
+//lets the tx interrupt drain it. False if a signal interrupted the wait
+pub unsafe  fn uart_putchar(c: u8) -> bool {
+    loop {
+        let guard = UART_LOCK.lock();
+        if uart_tx_w != uart_tx_r + UART_TX_BUF_SIZE {
+            uart_tx_buf[(uart_tx_w % UART_TX_BUF_SIZE) as usize] = c as u64;
+            uart_tx_w += 1;
+            uart_work();
+            return true;
+        }
+        if !UART_TX_WAIT.wait_then(|| drop(guard)) {
+            return false;
+        }
+    }
 }
 
-pub unsafe fn uart_getchar() -> u8 {
-    return if (read_reg(LSR) & 0x01)==1 {
-        read_reg(RHR)
-    } else {0}
+//for kernel prints, which may run without a current process
+pub unsafe fn uart_putchar_sync(c: u8) {
+    while read_reg(LSR) & LSR_TX_IDLE == 0 {}
+    write_reg(THR, c);
 }
 
-pub unsafe fn uart_work() {
-    while true{
+//blocks until there is input, None if a signal interrupted the wait
+pub unsafe fn uart_getchar() -> Option<u8> {
+    loop {
+        let guard = UART_LOCK.lock();
+        if uart_rx_r != uart_rx_w {
+            let c = uart_rx_buf[(uart_rx_r % UART_RX_BUF_SIZE) as usize];
+            uart_rx_r += 1;
+            return Some(c);
+        }
+        if !UART_RX_WAIT.wait_then(|| drop(guard)) {
+            return None;
+        }
+    }
+}
+
+//send as much as the transmitter takes, the rest goes out on the next tx interrupt.
+//Callers hold UART_LOCK
+unsafe fn uart_work() {
+    loop {
         if uart_tx_w== uart_tx_r {
             return
         }
@@ -72,8 +118,27 @@ pub unsafe fn uart_work() {
         }
         let ch = uart_tx_buf[(uart_tx_r%UART_TX_BUF_SIZE)as usize] as u8;
         uart_tx_r+=1;
+        UART_TX_WAIT.wake_all();
         write_reg(THR,ch);
     }
 }
 
-
+pub unsafe fn uart_intr() {
+    let _guard = UART_LOCK.lock();
+    //reading isr acknowledges a tx interrupt
+    read_reg(ISR);
+    let mut received = false;
+    while read_reg(LSR) & LSR_RX_READY != 0 {
+        let c = read_reg(RHR);
+        //drop input nobody has room for
+        if uart_rx_w != uart_rx_r + UART_RX_BUF_SIZE {
+            uart_rx_buf[(uart_rx_w % UART_RX_BUF_SIZE) as usize] = c;
+            uart_rx_w += 1;
+        }
+        received = true;
+    }
+    if received {
+        UART_RX_WAIT.wake_all();
+    }
+    uart_work();
+}
@@ -0,0 +1,261 @@
+use alloc::sync::Arc;
+use core::sync::atomic::{fence, Ordering};
+use lazy_static::lazy_static;
+use crate::io::block_device::{BLOCK_SIZE, BlockDevice};
+use crate::mm::frame_allocator::frame_alloc;
+use crate::mm::{page_num_to_addr, PhyAddr};
+use crate::println;
+use crate::sync::cell::Mutex;
+
+//virtio mmio interface of the first device on qemu virt, see virtio spec 4.2.2
+pub const VIRTIO0: usize = 0x10001000;
+pub const VIRTIO0_IRQ: u32 = 1;
+
+const VIRTIO_MMIO_MAGIC_VALUE: usize = 0x000;
+const VIRTIO_MMIO_VERSION: usize = 0x004;
+const VIRTIO_MMIO_DEVICE_ID: usize = 0x008;
+const VIRTIO_MMIO_VENDOR_ID: usize = 0x00c;
+const VIRTIO_MMIO_DEVICE_FEATURES: usize = 0x010;
+const VIRTIO_MMIO_DRIVER_FEATURES: usize = 0x020;
+const VIRTIO_MMIO_QUEUE_SEL: usize = 0x030;
+const VIRTIO_MMIO_QUEUE_NUM_MAX: usize = 0x034;
+const VIRTIO_MMIO_QUEUE_NUM: usize = 0x038;
+const VIRTIO_MMIO_QUEUE_READY: usize = 0x044;
+const VIRTIO_MMIO_QUEUE_NOTIFY: usize = 0x050;
+const VIRTIO_MMIO_INTERRUPT_STATUS: usize = 0x060;
+const VIRTIO_MMIO_INTERRUPT_ACK: usize = 0x064;
+const VIRTIO_MMIO_STATUS: usize = 0x070;
+const VIRTIO_MMIO_QUEUE_DESC_LOW: usize = 0x080;
+const VIRTIO_MMIO_QUEUE_DESC_HIGH: usize = 0x084;
+const VIRTIO_MMIO_DRIVER_DESC_LOW: usize = 0x090;
+const VIRTIO_MMIO_DRIVER_DESC_HIGH: usize = 0x094;
+const VIRTIO_MMIO_DEVICE_DESC_LOW: usize = 0x0a0;
+const VIRTIO_MMIO_DEVICE_DESC_HIGH: usize = 0x0a4;
+const VIRTIO_MMIO_CONFIG: usize = 0x100;
+
+const VIRTIO_CONFIG_S_ACKNOWLEDGE: u32 = 1;
+const VIRTIO_CONFIG_S_DRIVER: u32 = 2;
+const VIRTIO_CONFIG_S_DRIVER_OK: u32 = 4;
+const VIRTIO_CONFIG_S_FEATURES_OK: u32 = 8;
+
+const VIRTIO_BLK_F_RO: u32 = 5;
+const VIRTIO_BLK_F_SCSI: u32 = 7;
+const VIRTIO_BLK_F_CONFIG_WCE: u32 = 11;
+const VIRTIO_BLK_F_MQ: u32 = 12;
+const VIRTIO_F_ANY_LAYOUT: u32 = 27;
+const VIRTIO_RING_F_INDIRECT_DESC: u32 = 28;
+const VIRTIO_RING_F_EVENT_IDX: u32 = 29;
+
+const VRING_DESC_F_NEXT: u16 = 1;
+const VRING_DESC_F_WRITE: u16 = 2;
+
+const VIRTIO_BLK_T_IN: u32 = 0;
+const VIRTIO_BLK_T_OUT: u32 = 1;
+
+//number of descriptors, must be a power of two
+const NUM: usize = 8;
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct VirtqDesc {
+    addr: u64,
+    len: u32,
+    flags: u16,
+    next: u16,
+}
+
+#[repr(C)]
+struct VirtqAvail {
+    flags: u16,
+    idx: u16,
+    ring: [u16; NUM],
+    unused: u16,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct VirtqUsedElem {
+    id: u32,
+    len: u32,
+}
+
+#[repr(C)]
+struct VirtqUsed {
+    flags: u16,
+    idx: u16,
+    ring: [VirtqUsedElem; NUM],
+}
+
+#[repr(C)]
+struct VirtioBlkReq {
+    req_type: u32,
+    reserved: u32,
+    sector: u64,
+}
+
+unsafe fn read_reg(reg: usize) -> u32 {
+    ((VIRTIO0 + reg) as *const u32).read_volatile()
+}
+
+unsafe fn write_reg(reg: usize, val: u32) {
+    ((VIRTIO0 + reg) as *mut u32).write_volatile(val)
+}
+
+//the kernel maps physical memory identically, so frames double as DMA buffers
+fn dma_alloc() -> PhyAddr {
+    page_num_to_addr(frame_alloc().unwrap())
+}
+
+pub struct VirtIOBlock {
+    inner: Mutex<VirtIOBlockInner>,
+}
+
+struct VirtIOBlockInner {
+    desc: &'static mut [VirtqDesc; NUM],
+    avail: &'static mut VirtqAvail,
+    used: &'static mut VirtqUsed,
+    used_idx: u16,
+    //request header, status byte and data bounce buffer share one frame
+    req: &'static mut VirtioBlkReq,
+    status: &'static mut u8,
+    data: &'static mut [u8; BLOCK_SIZE],
+    capacity: u64,
+}
+
+impl VirtIOBlock {
+    pub fn new() -> Self {
+        unsafe {
+            assert_eq!(read_reg(VIRTIO_MMIO_MAGIC_VALUE), 0x74726976, "could not find virtio disk");
+            assert_eq!(read_reg(VIRTIO_MMIO_VERSION), 2, "virtio disk is not a modern device");
+            assert_eq!(read_reg(VIRTIO_MMIO_DEVICE_ID), 2, "virtio device is not a disk");
+            assert_eq!(read_reg(VIRTIO_MMIO_VENDOR_ID), 0x554d4551, "unknown virtio vendor");
+
+            let mut status = 0;
+            write_reg(VIRTIO_MMIO_STATUS, status);
+            status |= VIRTIO_CONFIG_S_ACKNOWLEDGE;
+            write_reg(VIRTIO_MMIO_STATUS, status);
+            status |= VIRTIO_CONFIG_S_DRIVER;
+            write_reg(VIRTIO_MMIO_STATUS, status);
+
+            let mut features = read_reg(VIRTIO_MMIO_DEVICE_FEATURES);
+            features &= !(1 << VIRTIO_BLK_F_RO);
+            features &= !(1 << VIRTIO_BLK_F_SCSI);
+            features &= !(1 << VIRTIO_BLK_F_CONFIG_WCE);
+            features &= !(1 << VIRTIO_BLK_F_MQ);
+            features &= !(1 << VIRTIO_F_ANY_LAYOUT);
+            features &= !(1 << VIRTIO_RING_F_EVENT_IDX);
+            features &= !(1 << VIRTIO_RING_F_INDIRECT_DESC);
+            write_reg(VIRTIO_MMIO_DRIVER_FEATURES, features);
+
+            status |= VIRTIO_CONFIG_S_FEATURES_OK;
+            write_reg(VIRTIO_MMIO_STATUS, status);
+            assert_ne!(read_reg(VIRTIO_MMIO_STATUS) & VIRTIO_CONFIG_S_FEATURES_OK, 0, "virtio disk FEATURES_OK unset");
+
+            write_reg(VIRTIO_MMIO_QUEUE_SEL, 0);
+            assert_eq!(read_reg(VIRTIO_MMIO_QUEUE_READY), 0, "virtio disk should not be ready");
+            let max = read_reg(VIRTIO_MMIO_QUEUE_NUM_MAX) as usize;
+            assert_ne!(max, 0, "virtio disk has no queue 0");
+            assert!(max >= NUM, "virtio disk max queue too short");
+
+            let desc = dma_alloc();
+            let avail = dma_alloc();
+            let used = dma_alloc();
+            let buffer = dma_alloc();
+
+            write_reg(VIRTIO_MMIO_QUEUE_NUM, NUM as u32);
+            write_reg(VIRTIO_MMIO_QUEUE_DESC_LOW, desc as u32);
+            write_reg(VIRTIO_MMIO_QUEUE_DESC_HIGH, (desc >> 32) as u32);
+            write_reg(VIRTIO_MMIO_DRIVER_DESC_LOW, avail as u32);
+            write_reg(VIRTIO_MMIO_DRIVER_DESC_HIGH, (avail >> 32) as u32);
+            write_reg(VIRTIO_MMIO_DEVICE_DESC_LOW, used as u32);
+            write_reg(VIRTIO_MMIO_DEVICE_DESC_HIGH, (used >> 32) as u32);
+            write_reg(VIRTIO_MMIO_QUEUE_READY, 1);
+
+            status |= VIRTIO_CONFIG_S_DRIVER_OK;
+            write_reg(VIRTIO_MMIO_STATUS, status);
+
+            let capacity = ((VIRTIO0 + VIRTIO_MMIO_CONFIG) as *const u64).read_volatile();
+            println!("[INFO]: virtio-blk capacity {} blocks", capacity);
+
+            VirtIOBlock {
+                inner: Mutex::new(VirtIOBlockInner {
+                    desc: &mut *(desc as *mut [VirtqDesc; NUM]),
+                    avail: &mut *(avail as *mut VirtqAvail),
+                    used: &mut *(used as *mut VirtqUsed),
+                    used_idx: 0,
+                    req: &mut *(buffer as *mut VirtioBlkReq),
+                    status: &mut *((buffer + 16) as *mut u8),
+                    data: &mut *((buffer + BLOCK_SIZE) as *mut [u8; BLOCK_SIZE]),
+                    capacity,
+                }),
+            }
+        }
+    }
+}
+
+impl VirtIOBlockInner {
+    //one request at a time: header -> data -> status chained through descriptors 0, 1, 2
+    fn rw(&mut self, block_id: usize, write: bool) {
+        assert!((block_id as u64) < self.capacity, "block {} out of disk range", block_id);
+        self.req.req_type = if write { VIRTIO_BLK_T_OUT } else { VIRTIO_BLK_T_IN };
+        self.req.reserved = 0;
+        self.req.sector = block_id as u64;
+        *self.status = 0xff;
+
+        self.desc[0] = VirtqDesc {
+            addr: self.req as *const VirtioBlkReq as u64,
+            len: core::mem::size_of::<VirtioBlkReq>() as u32,
+            flags: VRING_DESC_F_NEXT,
+            next: 1,
+        };
+        self.desc[1] = VirtqDesc {
+            addr: self.data.as_ptr() as u64,
+            len: BLOCK_SIZE as u32,
+            //device writes the buffer on reads
+            flags: if write { VRING_DESC_F_NEXT } else { VRING_DESC_F_NEXT | VRING_DESC_F_WRITE },
+            next: 2,
+        };
+        self.desc[2] = VirtqDesc {
+            addr: self.status as *const u8 as u64,
+            len: 1,
+            flags: VRING_DESC_F_WRITE,
+            next: 0,
+        };
+
+        self.avail.ring[self.avail.idx as usize % NUM] = 0;
+        fence(Ordering::SeqCst);
+        unsafe {
+            (&mut self.avail.idx as *mut u16).write_volatile(self.avail.idx.wrapping_add(1));
+        }
+        fence(Ordering::SeqCst);
+        unsafe {
+            write_reg(VIRTIO_MMIO_QUEUE_NOTIFY, 0);
+        }
+
+        while unsafe { (&self.used.idx as *const u16).read_volatile() } == self.used_idx {}
+        fence(Ordering::SeqCst);
+        self.used_idx = self.used_idx.wrapping_add(1);
+        unsafe {
+            write_reg(VIRTIO_MMIO_INTERRUPT_ACK, read_reg(VIRTIO_MMIO_INTERRUPT_STATUS) & 0x3);
+        }
+        assert_eq!(unsafe { (self.status as *const u8).read_volatile() }, 0, "virtio-blk request failed");
+    }
+}
+
+impl BlockDevice for VirtIOBlock {
+    fn read_block(&self, block_id: usize, buf: &mut [u8]) {
+        let mut inner = self.inner.lock();
+        inner.rw(block_id, false);
+        buf.copy_from_slice(&inner.data[..buf.len()]);
+    }
+
+    fn write_block(&self, block_id: usize, buf: &[u8]) {
+        let mut inner = self.inner.lock();
+        inner.data[..buf.len()].copy_from_slice(buf);
+        inner.rw(block_id, true);
+    }
+}
+
+lazy_static! {
+    pub static ref BLOCK_DEVICE: Arc<dyn BlockDevice> = Arc::new(VirtIOBlock::new());
+}
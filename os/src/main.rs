@@ -5,7 +5,6 @@
 
 extern crate alloc;
 
-mod loader;
 mod trap;
 mod syscall;
 mod sync;
@@ -13,17 +12,21 @@ mod io;
 mod mm;
 mod utility;
 mod process;
+mod fs;
 
 use core::arch::{asm, global_asm};
+use core::hint::spin_loop;
+use core::sync::atomic::{AtomicBool, Ordering};
 use riscv::register::*;
-use crate::loader::list_apps;
+use crate::fs::inode::list_apps;
+use crate::mm::kernel_space::KERNEL_SPACE;
 use crate::process::process::add_initproc;
+use crate::utility::{hart_id, parse_or};
 use crate::utility::timer::init_timer;
 
 pub const BOOTLOADER_STACK_SIZE: usize = 0x10000;
-pub const CPUS: usize = 1;
-
-global_asm!(include_str!("link_app.S"));
+//harts in use, set with CPUS at build time. Harts beyond it stay parked in _start
+pub const CPUS: usize = parse_or(option_env!("CPUS"), 1);
 
 #[link_section = ".bss.stack"]
 static mut BOOTLOADER_STACK_SPACE: [[u8; BOOTLOADER_STACK_SIZE]; CPUS] =
@@ -35,13 +38,20 @@ static mut BOOTLOADER_STACK_SPACE: [[u8; BOOTLOADER_STACK_SIZE]; CPUS] =
 #[link_section = ".text.entry"]
 unsafe extern "C" fn _start() {
     asm!(
+    "csrr t1, mhartid",
+    "li t0, {cpus}",
+    "bgeu t1, t0, 2f",
     "la sp, {bootloader_stack}",
     "li t0, {bootloader_stack_size}",
-    "csrr t1, mhartid",
     "addi t1, t1, 1",
     "mul t0, t0, t1",
     "add sp, sp, t0",
     "j {rust_start}",
+    //no stack for this hart
+    "2:",
+    "wfi",
+    "j 2b",
+    cpus = const CPUS,
     bootloader_stack = sym BOOTLOADER_STACK_SPACE,
     bootloader_stack_size = const BOOTLOADER_STACK_SIZE,
     rust_start = sym rust_start,
@@ -61,25 +71,41 @@ unsafe fn rust_start() -> ! {
 
     asm!("csrr tp, mhartid");
 
-    // init_timer();
+    init_timer();
 
+    //ecall from supervisor stays in machine mode for timervec
     asm!(
     "csrw mideleg, {mideleg}", // some bits could not be set by this method
     "csrw medeleg, {medeleg}",
     "mret",
-    medeleg = in(reg) !0,
+    medeleg = in(reg) !(1usize << 9),
     mideleg = in(reg) !0,
     options(noreturn),
     );
 }
 
+//set once the boot hart has memory, devices and initproc ready
+static BOOTED: AtomicBool = AtomicBool::new(false);
+
 #[no_mangle]
 extern "C" fn rust_main() {
+    if hart_id() != 0 {
+        //mm::init clears bss, nothing here may touch memory before that
+        while !BOOTED.load(Ordering::Acquire) {
+            spin_loop();
+        }
+        trap::init();
+        KERNEL_SPACE.lock().activate();
+        println!("[INFO]: Hart {} online", hart_id());
+        process::scheduler::run();
+    }
     trap::init();
     io::init();
     mm::init();
     println!("mm init");
+    io::block_init();
     list_apps();
     add_initproc();
+    BOOTED.store(true, Ordering::Release);
     process::scheduler::run();
 }
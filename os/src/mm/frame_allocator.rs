@@ -3,50 +3,11 @@ use alloc::vec::Vec;
 use core::borrow::{Borrow, BorrowMut};
 use core::ops::Deref;
 use lazy_static::lazy_static;
+use lose_core::frame::FrameAllocator;
 use crate::mm::{ceiling, ekernel, floor, MEMORY_END, PhysPageNum, read_bytes_array, to_ppn};
 use crate::{print, println};
-use crate::utility::recycle_counter::RecycleCounter;
 use crate::sync::cell::Mutex;
 
-pub struct FrameAllocator{
-    current: PhysPageNum,
-    end: PhysPageNum,
-    recycled: Vec<PhysPageNum>,
-}
-
-impl FrameAllocator{
-    pub fn new()->Self{
-        FrameAllocator{
-            current: 0,
-            end: 0,
-            recycled: vec![],
-        }
-    }
-
-    pub fn init(&mut self,begin:PhysPageNum,end:PhysPageNum){
-        self.current=begin;
-        self.end=end;
-    }
-
-    pub fn alloc(&mut self)->Option<PhysPageNum>{
-        if !self.recycled.is_empty(){
-            self.recycled.pop()
-        }else if self.current==self.end{
-            println!("[Warning]: Frame allocator running out");
-            None
-        }else {
-            self.current+=1;
-            Some(self.current-1)
-        }
-    }
-
-    pub fn dealloc(&mut self,recycle:PhysPageNum){
-        self.recycled.push(recycle)
-    }
-
-}
-
-
 lazy_static! {
     pub static ref FRAME_ALLOCATOR:Mutex<FrameAllocator>= Mutex::new(FrameAllocator::new());
 }
@@ -62,6 +23,9 @@ pub fn frame_allocator_init(){
 
 pub fn frame_alloc()->Option<PhysPageNum>{
     let result=FRAME_ALLOCATOR.lock().alloc();
+    if result.is_none(){
+        println!("[Warning]: Frame allocator running out");
+    }
     if let Some (ppn)=result{
         let bytes_array = read_bytes_array(ppn);
         for i in bytes_array {
@@ -71,6 +35,15 @@ pub fn frame_alloc()->Option<PhysPageNum>{
     result
 }
 
+//drops one reference, shared frames stay alive until the last one goes
 pub fn frame_dealloc(recycle: PhysPageNum){
     FRAME_ALLOCATOR.lock().dealloc(recycle)
+}
+
+pub fn frame_share(ppn: PhysPageNum){
+    FRAME_ALLOCATOR.lock().share(ppn)
+}
+
+pub fn frame_ref_count(ppn: PhysPageNum)->usize{
+    FRAME_ALLOCATOR.lock().ref_count(ppn)
 }
\ No newline at end of file
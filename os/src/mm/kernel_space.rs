@@ -5,6 +5,8 @@ use lazy_static::lazy_static;
 use riscv::register::satp;
 use crate::io::print;
 use crate::io::uart::UART0;
+use crate::io::virtio_blk::VIRTIO0;
+use crate::io::plic::PLIC;
 use crate::mm::frame_allocator::frame_alloc;
 use crate::mm::map_area::{MAP_PERM_R, MAP_PERM_W, MAP_PERM_X, MapType, MapArea};
 use crate::mm::pagetable::PageTable;
@@ -13,6 +15,7 @@ use crate::mm::map_area::MapType::{Framed, Identical};
 use crate::println;
 use crate::sync::cell::Mutex;
 use crate::syscall::VIRT_TEST;
+use crate::utility::recycle_counter::RecycleCounter;
 use crate::utility::timer::CLINT;
 
 pub struct KernelSpace {
@@ -136,11 +139,27 @@ impl KernelSpace {
         );
         self.page_table.area_mapping(&mut clint_area);
         self.areas.push(clint_area);
+        let mut virtio_area = MapArea::new(
+            VIRTIO0,
+            VIRTIO0 + 0x1000,
+            Identical,
+            MAP_PERM_R | MAP_PERM_W
+        );
+        self.page_table.area_mapping(&mut virtio_area);
+        self.areas.push(virtio_area);
+        let mut plic_area = MapArea::new(
+            PLIC,
+            PLIC + 0x400000,
+            Identical,
+            MAP_PERM_R | MAP_PERM_W
+        );
+        self.page_table.area_mapping(&mut plic_area);
+        self.areas.push(plic_area);
         self.activate();
     }
 
-    pub fn kernel_stack_apply(&mut self, pid: usize) -> usize {
-        let top = kernel_stack_top(pid);
+    pub fn kernel_stack_apply(&mut self, id: usize) -> usize {
+        let top = kernel_stack_top(id);
         let bottom = top - KERNEL_STACK_SIZE;
         println!("Kernel stack apply from {:#x} to {:#x}",bottom, top);
         self.page_table.area_mapping(&mut MapArea::new(bottom, top, Framed, MAP_PERM_R | MAP_PERM_W));
@@ -149,10 +168,54 @@ impl KernelSpace {
 
 }
 
-pub fn kernel_stack_top(pid: usize) -> usize {
-    TRAMPOLINE - (pid+1) * (KERNEL_STACK_SIZE + PAGE_SIZE)
+lazy_static! {
+    static ref KSTACK_ALLOCATOR: Mutex<RecycleCounter> = Mutex::new(RecycleCounter::new(usize::MAX - 1));
+    //ids below this have their stack mapped
+    static ref KSTACK_MAPPED: Mutex<usize> = Mutex::new(0);
+}
+
+//a kernel stack for a new thread, identified by the returned id. Stacks stay
+//mapped for good and are handed out again, unmapping one would leave stale
+//tlb entries on the other harts
+pub fn kernel_stack_alloc() -> usize {
+    let id = KSTACK_ALLOCATOR.lock().alloc().unwrap();
+    let mut mapped = KSTACK_MAPPED.lock();
+    if id == *mapped {
+        KERNEL_SPACE.lock().kernel_stack_apply(id);
+        *mapped += 1;
+    }
+    id
+}
+
+//only once no hart runs on the stack any more
+pub fn kernel_stack_dealloc(id: usize) {
+    KSTACK_ALLOCATOR.lock().dealloc(id);
+}
+
+pub fn kernel_stack_top(id: usize) -> usize {
+    TRAMPOLINE - (id+1) * (KERNEL_STACK_SIZE + PAGE_SIZE)
+}
+
+pub fn kernel_stack_bottom(id: usize) -> usize {
+    TRAMPOLINE - (id+1) * (KERNEL_STACK_SIZE + PAGE_SIZE) - KERNEL_STACK_SIZE
 }
 
-pub fn kernel_stack_bottom(pid: usize) -> usize {
-    TRAMPOLINE - (pid+1) * (KERNEL_STACK_SIZE + PAGE_SIZE) - KERNEL_STACK_SIZE
-}
\ No newline at end of file
+//every kernel stack has an unmapped guard page right below it,
+//returns the id of the stack that ran into va
+pub fn kernel_stack_guard_owner(va: usize) -> Option<usize> {
+    let slot = KERNEL_STACK_SIZE + PAGE_SIZE;
+    if va >= TRAMPOLINE {
+        return None;
+    }
+    let n = (TRAMPOLINE - va + slot - 1) / slot;
+    if n < 2 {
+        return None;
+    }
+    let id = n - 2;
+    let guard_top = kernel_stack_bottom(id);
+    if guard_top - PAGE_SIZE <= va && va < guard_top {
+        Some(id)
+    } else {
+        None
+    }
+}
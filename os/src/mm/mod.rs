@@ -9,27 +9,18 @@ pub mod pagetable;
 pub mod frame_allocator;
 pub mod map_area;
 pub mod kernel_space;
+pub mod user_ptr;
+pub mod tlb;
 
-pub type PhysPageNum = usize;
-pub type PhyAddr = usize;
-pub type VirPageNum = usize;
-pub type VirAddr = usize;
-
-pub const PA_WIDTH: usize = 56;
-pub const VA_WIDTH: usize = 39;
-pub const PPN_WIDTH: usize = 44;
-pub const VPN_WIDTH: usize = 27;
-pub const PA_OFFSET: usize = 11;
-pub const VA_OFFSET: usize = 9;
-pub const PAGE_SIZE: usize = 1 << 12;
-pub const PAGE_WIDTH: usize = 12;
-pub const MAX_VA: usize = usize::MAX - PAGE_SIZE;
+pub use lose_core::addr::*;
 
 pub const TRAMPOLINE: usize = MAX_VA - PAGE_SIZE + 1;
 pub const TRAP_CONTEXT: usize = TRAMPOLINE - PAGE_SIZE;
 pub const MEMORY_END: usize = 0x85000000;
 
+//initial user stack, it grows on demand up to the stack rlimit
 pub const USER_STACK_SIZE: usize = 0x10000;
+pub const USER_STACK_LIMIT: usize = 0x80_0000;
 pub const KERNEL_STACK_SIZE: usize = 0x10000;
 pub const KERNEL_HEAP_SIZE: usize = 0x200_0000;
 
@@ -41,45 +32,12 @@ extern "C" {
     pub fn sdata();
     pub fn edata();
     pub fn sbss_with_stack();
+    pub fn sbss();
     pub fn ebss();
     pub fn ekernel();
     pub fn strampoline();
 }
 
-pub fn to_pa(v: usize) -> PhyAddr {
-    v & ((1 << PA_WIDTH )- 1)
-}
-
-pub fn to_va(v: usize) -> VirAddr {
-    v & ((1 << VA_WIDTH) - 1)
-}
-
-pub fn to_ppn(v: usize) -> PhysPageNum {
-    v & ((1 << PPN_WIDTH) - 1)
-}
-
-pub fn to_vpn(v: usize) -> VirPageNum {
-    v & ((1 << VA_WIDTH) - 1)
-}
-
-pub fn addr_to_page_num(v: usize) -> usize {
-    v >> PAGE_WIDTH
-}
-
-pub fn page_num_to_addr(v: usize) -> usize { v << PAGE_WIDTH }
-
-pub fn floor(v: usize) -> usize {
-    v >>PAGE_WIDTH
-}
-
-pub fn ceiling(v: usize) -> usize {
-    ((v - 1) / PAGE_SIZE) + 1
-}
-
-pub fn get_offset(v: usize) -> usize {
-    v & (PAGE_SIZE - 1)
-}
-
 pub fn read_frame(ppn: PhysPageNum) -> &'static mut [u8] {
     let pa: PhyAddr = page_num_to_addr(ppn);
     unsafe { core::slice::from_raw_parts_mut(pa as *mut u8, 4096) }
@@ -99,21 +57,10 @@ pub fn get_mut<T>(pa: PhyAddr) -> &'static mut T {
     unsafe { (pa as *mut T).as_mut().unwrap() }
 }
 
-pub fn get_vir_indexes(v: VirPageNum) -> [usize; 3] {
-    [v >> (2 * VA_OFFSET) & ((1 << VA_OFFSET) - 1),
-        v >> VA_OFFSET & ((1 << VA_OFFSET) - 1),
-        v & ((1 << VA_OFFSET) - 1)]
-}
-
-pub fn get_phys_indexes(v: PhysPageNum) -> [usize; 3] {
-    [v & ((1 << PA_OFFSET) - 1),
-        v >> PA_OFFSET & ((1 << PA_OFFSET) - 1),
-        v >> (2 * PA_OFFSET) & ((1 << PA_OFFSET) - 1)]
-}
-
 pub fn init() {
     unsafe {
-        core::slice::from_raw_parts_mut(sbss_with_stack as *mut u8, ebss as usize - sbss_with_stack as usize).fill(0);
+        //boot stacks and the timer scratch area live below sbss and are already in use
+        core::slice::from_raw_parts_mut(sbss as *mut u8, ebss as usize - sbss as usize).fill(0);
     }
     heap_init();
     println!("[INFO]: Buddy Allocator online");
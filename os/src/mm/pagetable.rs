@@ -1,4 +1,3 @@
-use alloc::string::String;
 use alloc::vec;
 use alloc::vec::Vec;
 use core::mem::size_of;
@@ -51,6 +50,10 @@ impl PageTableEntry {
     pub fn is_executable(&self) -> bool {
         (self.0 >> 3) & 1 == 1
     }
+
+    pub fn is_user(&self) -> bool {
+        (self.0 >> 4) & 1 == 1
+    }
 }
 
 pub struct PageTable {
@@ -75,21 +78,24 @@ impl PageTable {
 
     pub fn area_mapping(&mut self, area: &mut MapArea) {
         for vpn in area.start..area.end {
-            match area.map_type {
-                MapType::Identical => {
-                    let frame: PhysPageNum = vpn;
-                    self.map(vpn, frame, area.map_perm);
-                    area.frame_mapping.insert(vpn, frame);
-                }
-                MapType::Framed => {
-                    let frame = frame_alloc().unwrap();
-                    self.map(vpn, frame, area.map_perm);
-                    area.frame_mapping.insert(vpn, frame);
-                }
-            }
+            self.page_mapping(area, vpn);
         }
     }
 
+    //back a single page of area, false if out of frames
+    pub fn page_mapping(&mut self, area: &mut MapArea, vpn: VirPageNum) -> bool {
+        let frame = match area.map_type {
+            MapType::Identical => vpn,
+            MapType::Framed => match frame_alloc() {
+                Some(frame) => frame,
+                None => return false,
+            },
+        };
+        self.map(vpn, frame, area.map_perm);
+        area.frame_mapping.insert(vpn, frame);
+        true
+    }
+
     pub fn token(&self)->usize{
         8usize << 60 | self.root
     }
@@ -148,17 +154,32 @@ impl PageTable {
         result
     }
 
+    //a page without R, W or X (PROT_NONE) gets no valid entry, it would
+    //point to another level. The levels above are created all the same
     pub fn map(&mut self, vpn: VirPageNum, ppn: PhysPageNum, flag: usize) {
         let pte = self.find_pte_create(vpn).unwrap();
         // println!("vpn {} mapped to ppn{}",vpn,ppn);
         assert!(!pte.is_valid(), "vpn {:?} is mapped before mapping", vpn);
         // println!("pagetable mapping flag {:#b}",flag|PTE_FLAG_V);
-        *pte = PageTableEntry::new(ppn, flag | PTE_FLAG_V);
+        if flag & (PTE_FLAG_R | PTE_FLAG_W | PTE_FLAG_X) != 0 {
+            *pte = PageTableEntry::new(ppn, flag | PTE_FLAG_V);
+        }
+    }
+
+    //change the frame or flags of a mapped page. Without R, W or X the entry
+    //is cleared and the frame stays with the area, see map
+    pub fn remap(&self, vpn: VirPageNum, ppn: PhysPageNum, flag: usize) {
+        let pte = self.find_pte(vpn).unwrap();
+        *pte = if flag & (PTE_FLAG_R | PTE_FLAG_W | PTE_FLAG_X) != 0 {
+            PageTableEntry::new(ppn, flag | PTE_FLAG_V)
+        } else {
+            PageTableEntry::empty()
+        };
     }
 
+    //pages without access have no valid entry to clear, see map
     pub fn unmap(&mut self, vpn: VirPageNum) {
         let pte = self.find_pte(vpn).unwrap();
-        assert!(pte.is_valid(), "vpn {:?} is invalid before unmapping", vpn);
         *pte = PageTableEntry::empty();
     }
 
@@ -168,42 +189,4 @@ impl PageTable {
         }
         None
     }
-
-    pub fn translated_str(&self,ptr: *const u8) -> String {
-        let mut string = String::new();
-        let mut va = to_va(ptr as usize) ;
-        loop {
-            let ch: u8 = *(get_mut(self.translate_va(va).unwrap()));
-            if ch == 0 {
-                break;
-            } else {
-                string.push(ch as char);
-                va += 1;
-            }
-        }
-        string
-    }
-
-    pub fn translated_byte_buffer(&self, ptr: *const u8, len: usize) -> Vec<&'static mut [u8]> {
-        let mut start = ptr as usize;
-        let end = start + len;
-        let mut v = Vec::new();
-        let mut iter =0;
-        while start < end {
-            iter+=1;
-            let start_va = to_va(start);
-            let mut vpn: VirPageNum = floor(start_va);
-            let ppn = self.find_pte(vpn).unwrap().ppn();
-            vpn+=1;
-            let mut end_va: VirAddr = page_num_to_addr(vpn);
-            end_va = end_va.min(to_va(end));
-            if get_offset(end_va) == 0 {
-                v.push(&mut read_frame(ppn)[get_offset(start_va)..]);
-            } else {
-                v.push(&mut read_frame(ppn)[get_offset(start_va)..get_offset(end_va)]);
-            }
-            start = end_va;
-        }
-        v
-    }
 }
@@ -0,0 +1,47 @@
+use core::arch::asm;
+use core::hint::spin_loop;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use crate::CPUS;
+use crate::utility::hart_id;
+use crate::utility::timer::send_ipi;
+
+//token of the space each hart runs in user mode, 0 while it is in the kernel
+static USER_TOKEN: [AtomicUsize; CPUS] = [const { AtomicUsize::new(0) }; CPUS];
+//traps from user mode taken by each hart, __alltraps flushes the tlb on every one
+static USER_TRAPS: [AtomicUsize; CPUS] = [const { AtomicUsize::new(0) }; CPUS];
+
+//right before __restore, which flushes the tlb as it switches to the space
+pub fn enter_user(token: usize) {
+    USER_TOKEN[hart_id()].store(token, Ordering::SeqCst);
+}
+
+//first thing in trap_handler
+pub fn leave_user() {
+    let hartid = hart_id();
+    USER_TOKEN[hartid].store(0, Ordering::SeqCst);
+    USER_TRAPS[hartid].fetch_add(1, Ordering::SeqCst);
+}
+
+//drop cached translations of the space on every hart. The ones running it in user
+//mode get an interrupt and are waited for until they have trapped into the kernel,
+//the others flush on their way back to user mode
+pub fn shootdown(token: usize) {
+    unsafe {
+        asm!("sfence.vma");
+    }
+    let mut traps: [Option<usize>; CPUS] = [None; CPUS];
+    for hartid in (0..CPUS).filter(|h| *h != hart_id()) {
+        if USER_TOKEN[hartid].load(Ordering::SeqCst) == token {
+            traps[hartid] = Some(USER_TRAPS[hartid].load(Ordering::SeqCst));
+            send_ipi(hartid);
+        }
+    }
+    for (hartid, count) in traps.iter().enumerate() {
+        if let Some(count) = count {
+            while USER_TOKEN[hartid].load(Ordering::SeqCst) == token
+                && USER_TRAPS[hartid].load(Ordering::SeqCst) == *count {
+                spin_loop();
+            }
+        }
+    }
+}
@@ -0,0 +1,148 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+use core::mem::size_of;
+use crate::mm::{floor, get_offset, page_num_to_addr, read_frame, USER_STACK_SIZE, VirAddr};
+use crate::process::process::Process;
+use crate::syscall::errno::Errno;
+use crate::syscall::errno::Errno::{E2BIG, EFAULT, EINVAL, ENAMETOOLONG};
+
+//longest string read_user_str copies in, the nul included
+pub const MAX_USER_STR: usize = 4096;
+//read_user_str_array stops here, more would not fit the initial stack anyway
+pub const MAX_USER_STRS_SIZE: usize = USER_STACK_SIZE;
+
+//every page of [va, va + len) backed and mapped with U plus R, and W when writing.
+//Returns the physical pieces split at page boundaries
+pub fn user_buffers(prc: &mut Process, va: VirAddr, len: usize, write: bool) -> Result<Vec<&'static mut [u8]>, Errno> {
+    let end = va.checked_add(len).ok_or(EFAULT)?;
+    if len == 0 {
+        return Ok(Vec::new());
+    }
+    if !prc.prepare_user_range(va, len, write) {
+        return Err(EFAULT);
+    }
+    let mut buffers = Vec::new();
+    let mut start = va;
+    while start < end {
+        let vpn = floor(start);
+        let pte = match prc.page_table.find_pte(vpn) {
+            Some(pte) if pte.is_valid() && pte.is_user() && pte.is_readable() => pte,
+            _ => return Err(EFAULT),
+        };
+        if write && !pte.is_writable() {
+            return Err(EFAULT);
+        }
+        let page_end = end.min(page_num_to_addr(vpn + 1));
+        let offset = get_offset(start);
+        buffers.push(&mut read_frame(pte.ppn())[offset..offset + page_end - start]);
+        start = page_end;
+    }
+    Ok(buffers)
+}
+
+pub fn copy_from_user(prc: &mut Process, dst: &mut [u8], src: VirAddr) -> Result<(), Errno> {
+    let mut ptr = 0;
+    for buf in user_buffers(prc, src, dst.len(), false)? {
+        dst[ptr..ptr + buf.len()].copy_from_slice(buf);
+        ptr += buf.len();
+    }
+    Ok(())
+}
+
+pub fn copy_to_user(prc: &mut Process, dst: VirAddr, src: &[u8]) -> Result<(), Errno> {
+    let mut ptr = 0;
+    for buf in user_buffers(prc, dst, src.len(), true)? {
+        buf.copy_from_slice(&src[ptr..ptr + buf.len()]);
+        ptr += buf.len();
+    }
+    Ok(())
+}
+
+//a single T in user memory, T must be plain data
+pub struct UserPtr<T> {
+    va: VirAddr,
+    _marker: PhantomData<T>,
+}
+
+impl<T: Copy> UserPtr<T> {
+    pub fn new(ptr: *const T) -> Self {
+        UserPtr { va: ptr as VirAddr, _marker: PhantomData }
+    }
+
+    pub fn is_null(&self) -> bool {
+        self.va == 0
+    }
+
+    pub fn read(&self, prc: &mut Process) -> Result<T, Errno> {
+        let mut value = core::mem::MaybeUninit::<T>::uninit();
+        let bytes = unsafe { core::slice::from_raw_parts_mut(value.as_mut_ptr() as *mut u8, size_of::<T>()) };
+        copy_from_user(prc, bytes, self.va)?;
+        Ok(unsafe { value.assume_init() })
+    }
+
+    pub fn write(&self, prc: &mut Process, value: T) -> Result<(), Errno> {
+        let bytes = unsafe { core::slice::from_raw_parts(&value as *const T as *const u8, size_of::<T>()) };
+        copy_to_user(prc, self.va, bytes)
+    }
+
+    //the next T, for walking user arrays
+    pub fn add(&self, count: usize) -> Self {
+        UserPtr { va: self.va + count * size_of::<T>(), _marker: PhantomData }
+    }
+}
+
+//len bytes of user memory, like the buffer of read/write
+pub struct UserSlice {
+    va: VirAddr,
+    len: usize,
+}
+
+impl UserSlice {
+    pub fn new(ptr: *const u8, len: usize) -> Self {
+        UserSlice { va: ptr as VirAddr, len }
+    }
+
+    pub fn buffers(&self, prc: &mut Process, write: bool) -> Result<Vec<&'static mut [u8]>, Errno> {
+        user_buffers(prc, self.va, self.len, write)
+    }
+}
+
+//nul terminated UTF-8 string, checked a byte at a time since its length is unknown
+pub fn read_user_str(prc: &mut Process, ptr: *const u8) -> Result<String, Errno> {
+    let mut bytes = Vec::new();
+    let mut ch = UserPtr::new(ptr);
+    loop {
+        match ch.read(prc)? {
+            0 => return String::from_utf8(bytes).map_err(|_| EINVAL),
+            _ if bytes.len() + 1 == MAX_USER_STR => return Err(ENAMETOOLONG),
+            c => bytes.push(c),
+        }
+        ch = ch.add(1);
+    }
+}
+
+//null terminated array of string pointers like argv, a null array is empty
+pub fn read_user_str_array(prc: &mut Process, ptr: *const usize) -> Result<Vec<String>, Errno> {
+    let mut strings = Vec::new();
+    let mut size = 0;
+    let mut item = UserPtr::new(ptr);
+    while !item.is_null() {
+        let str_ptr = item.read(prc)?;
+        if str_ptr == 0 {
+            break;
+        }
+        let string = match read_user_str(prc, str_ptr as *const u8) {
+            Err(ENAMETOOLONG) => return Err(E2BIG),
+            result => result?,
+        };
+        //the pointer counts as well, an array of empty strings is not free
+        size += string.len() + 1 + size_of::<usize>();
+        if size > MAX_USER_STRS_SIZE {
+            return Err(E2BIG);
+        }
+        strings.push(string);
+        item = item.add(1);
+    }
+    Ok(strings)
+}
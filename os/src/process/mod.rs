@@ -2,4 +2,8 @@ use crate::process::scheduler::{SCHEDULER, Scheduler};
 
 pub mod context;
 pub mod process;
-pub mod scheduler;
\ No newline at end of file
+pub mod processor;
+pub mod scheduler;
+pub mod signal;
+pub mod thread;
+pub mod wait_queue;
\ No newline at end of file
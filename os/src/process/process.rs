@@ -1,3 +1,5 @@
+use alloc::collections::BTreeMap;
+use alloc::string::String;
 use alloc::sync::{Arc, Weak};
 use alloc::vec;
 use alloc::vec::Vec;
@@ -5,38 +7,62 @@ use core::arch::asm;
 use core::borrow::BorrowMut;
 use core::cell::RefMut;
 use core::cmp::min;
+use core::mem::size_of;
 use lazy_static::lazy_static;
+use lose_core::elf::{AT_ENTRY, AT_NULL, AT_PAGESZ, AT_PHDR, AT_PHENT, AT_PHNUM, AT_RANDOM, heap_base, parse_elf, user_stack_range, USER_STACK_TOP};
 use riscv::register::satp;
+use crate::fs::File;
+use crate::fs::stdio::{Stdin, Stdout};
 use crate::io::print;
-use crate::loader::get_app_data_by_name;
-use crate::mm::frame_allocator::frame_alloc;
-use crate::mm::map_area::{MAP_PERM_R, MAP_PERM_U, MAP_PERM_W, MAP_PERM_X, MapArea, MapType};
+use crate::fs::inode::open_file;
+use crate::fs::O_RDONLY;
+use crate::mm::frame_allocator::{frame_alloc, frame_dealloc, frame_ref_count, frame_share};
+use crate::mm::map_area::{MAP_PERM_R, MAP_PERM_U, MAP_PERM_W, MAP_PERM_X, MapArea, MapType, prot_to_perm};
 use crate::mm::pagetable::PageTable;
-use crate::mm::{addr_to_page_num, MEMORY_END, page_num_to_addr, PAGE_SIZE, PhysPageNum, read_frame, TRAMPOLINE, TRAP_CONTEXT, USER_STACK_SIZE, VirAddr, VirPageNum};
+use crate::mm::{addr_to_page_num, ceiling, floor, MEMORY_END, page_num_to_addr, PAGE_SIZE, PhysPageNum, read_frame, TRAMPOLINE, TRAP_CONTEXT, USER_STACK_LIMIT, USER_STACK_SIZE, VirAddr, VirPageNum};
 use crate::mm::kernel_space::{KERNEL_SPACE, kernel_stack_top};
+use crate::mm::tlb::shootdown;
+use crate::mm::user_ptr::copy_to_user;
 use crate::mm::map_area::MapType::Framed;
 use crate::println;
-use crate::process::context::Context;
-use crate::process::process::ProcessStatus::{Ready};
 use crate::process::scheduler::SCHEDULER;
+use crate::process::signal::SignalState;
+use crate::process::thread::{MAX_THREADS, Thread, ThreadStatus, trap_cxt_bottom};
+use crate::process::wait_queue::WaitQueue;
 use crate::sync::cell::{Mutex, MutexGuard};
+use crate::sync::condvar::Condvar;
+use crate::sync::mutex::UserMutex;
+use crate::sync::semaphore::Semaphore;
+use crate::syscall::errno::Errno;
 use crate::trap::trap_context::TrapContext;
 use crate::trap::trap_handler;
 use crate::utility::recycle_counter::RecycleCounter;
+use crate::utility::timer::get_time;
 
 lazy_static!(
     pub static ref PID_ALLOCATOR: Mutex<RecycleCounter> = Mutex::new(RecycleCounter::new(usize::MAX - 1));
+    //live processes by pid, for kill. Entries go away when the process exits
+    static ref PID2PRC: Mutex<BTreeMap<usize, Arc<ProcessWrapper>>> = Mutex::new(BTreeMap::new());
 );
 
-#[derive(PartialEq)]
-pub enum ProcessStatus {
-    Running,
-    Ready,
-    Dead,
+pub fn insert_prc(prc: &Arc<ProcessWrapper>) {
+    PID2PRC.lock().insert(prc.pid, prc.clone());
+}
+
+pub fn remove_prc(pid: usize) {
+    PID2PRC.lock().remove(&pid);
+}
+
+pub fn pid2prc(pid: usize) -> Option<Arc<ProcessWrapper>> {
+    PID2PRC.lock().get(&pid).cloned()
 }
 
 pub struct ProcessWrapper{
     pub(crate) pid:usize,
+    //the process blocks here in waitpid until one of its children exits
+    pub child_exit: WaitQueue,
+    //threads block here in waittid until another thread exits
+    pub thread_exit: WaitQueue,
     inner: Mutex<Process>
 }
 
@@ -44,6 +70,8 @@ impl ProcessWrapper{
     pub fn new(prc: Process)->Self{
         ProcessWrapper{
             pid:prc.pid,
+            child_exit: WaitQueue::new(),
+            thread_exit: WaitQueue::new(),
             inner: Mutex::new(prc)
         }
     }
@@ -53,19 +81,62 @@ impl ProcessWrapper{
     }
 }
 
+pub const RLIMIT_STACK: usize = 3;
+//open files of one process, like the usual RLIMIT_NOFILE
+pub const MAX_FDS: usize = 1024;
+const HEAP_PERM: usize = MAP_PERM_U | MAP_PERM_R | MAP_PERM_W;
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct RLimit {
+    pub cur: usize,
+    pub max: usize,
+}
+
 pub struct Process {
     pub pid: usize,
     pub exit_code: i32,
-    pub context: Context,
-    pub status: ProcessStatus,
+    //exited but not yet reaped by waitpid
+    pub zombie: bool,
     pub page_table: PageTable,
     pub areas: Vec<MapArea>,
     pub parent: Option<Weak<ProcessWrapper>>,
     pub children: Vec<Arc<ProcessWrapper>>,
-    pub trap_context_ppn: PhysPageNum,
+    //indexed by tid, exited threads stay until waittid reaps them
+    pub threads: Vec<Option<Arc<Thread>>>,
+    pub tid_allocator: RecycleCounter,
+    //heap area is [heap_bottom, program_brk) rounded up to pages
+    pub heap_bottom: usize,
+    pub program_brk: usize,
+    //page right below the user stack, never mapped so overflows fault there
+    pub stack_guard: VirPageNum,
+    pub stack_rlimit: RLimit,
+    pub fd_table: Vec<Option<Arc<dyn File>>>,
+    pub signals: SignalState,
+    //synchronization objects for the threads, indexed by the id create returned
+    pub mutex_list: Vec<Arc<UserMutex>>,
+    pub semaphore_list: Vec<Arc<Semaphore>>,
+    pub condvar_list: Vec<Arc<Condvar>>,
 }
 
 impl Process {
+    //lowest free fd, EMFILE once MAX_FDS are open
+    pub fn alloc_fd(&mut self, file: Arc<dyn File>) -> Result<usize, Errno> {
+        if let Some(fd) = self.fd_table.iter().position(|f| f.is_none()) {
+            self.fd_table[fd] = Some(file);
+            Ok(fd)
+        } else if self.fd_table.len() < MAX_FDS {
+            self.fd_table.push(Some(file));
+            Ok(self.fd_table.len() - 1)
+        } else {
+            Err(Errno::EMFILE)
+        }
+    }
+
+    pub fn get_file(&self, fd: usize) -> Option<Arc<dyn File>> {
+        self.fd_table.get(fd).and_then(|f| f.clone())
+    }
+
     fn area_loading(&mut self, area: &mut MapArea, data: Option<&[u8]>) {
         if data.is_none() { return; }
         let data=data.unwrap();
@@ -82,98 +153,450 @@ impl Process {
 
     pub fn frame_recycle(&mut self) {
         for (_,area) in self.areas.iter().enumerate() {
-            area.recycle();
-        }
-    }
-
-    pub fn get_trap_cxt(&self) -> &'static mut TrapContext {
-        unsafe {
-            (page_num_to_addr(self.trap_context_ppn) as *mut TrapContext).as_mut().unwrap()
+            area.recycle(frame_dealloc);
         }
     }
 
-    pub fn load_trap_cxt_trampoline(&mut self) {
-        let mut area=MapArea::new(
-            TRAP_CONTEXT.into(),
-            TRAMPOLINE.into(),
+    //trap context page of thread tid, returns its frame
+    pub fn map_trap_cxt(&mut self, tid: usize) -> PhysPageNum {
+        let bottom = trap_cxt_bottom(tid);
+        let mut area = MapArea::new(
+            bottom,
+            bottom + PAGE_SIZE,
             MapType::Framed,
             MAP_PERM_R | MAP_PERM_W,
         );
-
         self.page_table.area_mapping(&mut area);
         self.areas.push(area);
-        self.page_table.load_trampoline();
-        self.trap_context_ppn = self.page_table.find_pte(addr_to_page_num(TRAP_CONTEXT)).unwrap().ppn();
+        self.page_table.find_pte(addr_to_page_num(bottom)).unwrap().ppn()
+    }
+
+    //first free slot below the furthest the main stack may grow, every stack
+    //keeps an unmapped guard page under it like the main one
+    pub fn alloc_thread_stack(&mut self) -> Option<(VirAddr, VirAddr)> {
+        for slot in 0..MAX_THREADS {
+            let top = USER_STACK_TOP - USER_STACK_LIMIT - PAGE_SIZE - slot * (USER_STACK_SIZE + PAGE_SIZE);
+            let bottom = top - USER_STACK_SIZE;
+            let (start_vpn, end_vpn) = (floor(bottom) - 1, ceiling(top));
+            if !self.areas.iter().any(|a| a.start < end_vpn && start_vpn < a.end) {
+                self.areas.push(MapArea::new(bottom, top, Framed, MAP_PERM_U | MAP_PERM_R | MAP_PERM_W));
+                return Some((bottom, top));
+            }
+        }
+        None
     }
 
-    pub fn load_elf(elf_data: &[u8]) -> Process {
+    //unmap and free the area starting at start, if there is one
+    fn remove_area(&mut self, start: VirAddr) {
+        let start_vpn = floor(start);
+        if let Some(idx) = self.areas.iter().position(|a| a.start == start_vpn) {
+            let area = self.areas.remove(idx);
+            for vpn in area.frame_mapping.keys() {
+                self.page_table.unmap(*vpn);
+            }
+            self.flush_tlb();
+            area.recycle(frame_dealloc);
+        }
+    }
+
+    //user stack and trap context of an exited thread, the tid stays taken until it is reaped
+    pub fn release_thread_res(&mut self, tid: usize, ustack: Option<(VirAddr, VirAddr)>) {
+        if let Some((bottom, _)) = ustack {
+            self.remove_area(bottom);
+        }
+        self.remove_area(trap_cxt_bottom(tid));
+    }
+
+    pub fn load_elf(elf_data: &[u8], args: &[String], envs: &[String]) -> Arc<ProcessWrapper> {
         let pg_root = frame_alloc().unwrap();
         let mut page_table = PageTable::new(pg_root);
         let pid: usize;
         unsafe {
             pid = PID_ALLOCATOR.lock().alloc().unwrap();
         }
-        KERNEL_SPACE.lock().kernel_stack_apply(pid);
         let mut process = Process {
             pid,
             exit_code: 0,
-            context: Context::goto_trap_return(kernel_stack_top(pid)),
-            status: Ready,
+            zombie: false,
             page_table,
             areas: vec![],
             parent: None,
             children: vec![],
-            trap_context_ppn: 0,
+            threads: vec![],
+            tid_allocator: RecycleCounter::new(MAX_THREADS),
+            heap_bottom: 0,
+            program_brk: 0,
+            stack_guard: 0,
+            stack_rlimit: RLimit { cur: USER_STACK_LIMIT, max: USER_STACK_LIMIT },
+            signals: SignalState::new(),
+            mutex_list: vec![],
+            semaphore_list: vec![],
+            condvar_list: vec![],
+            fd_table: vec![
+                // 0 -> stdin
+                Some(Arc::new(Stdin)),
+                // 1 -> stdout
+                Some(Arc::new(Stdout)),
+                // 2 -> stderr
+                Some(Arc::new(Stdout)),
+            ],
         };
-        process.load_trap_cxt_trampoline();
-        process.elf_parser(elf_data);
-        process
+        process.page_table.load_trampoline();
+        let prc = Arc::new(ProcessWrapper::new(process));
+        let mut prc_inner = prc.inner();
+        let thread = Thread::new(&prc, &mut prc_inner, false, 0).unwrap();
+        prc_inner.elf_parser(&thread, elf_data, args, envs).unwrap();
+        drop(prc_inner);
+        prc
     }
 
-    pub fn exec(& mut self, elf_data: &[u8]) {
+    //marks every thread but tid Dead and forgets them. Those running on another
+    //hart stop at their next trap, the caller waits for that before freeing memory
+    pub fn kill_other_threads(&mut self, tid: usize) -> Vec<Arc<Thread>> {
+        let mut killed = Vec::new();
+        for (idx, slot) in self.threads.iter_mut().enumerate() {
+            if idx == tid {
+                continue;
+            }
+            if let Some(other) = slot.take() {
+                other.inner().status = ThreadStatus::Dead;
+                self.tid_allocator.dealloc(idx);
+                killed.push(other);
+            }
+        }
+        killed
+    }
+
+    //only the main thread may exec, after kill_other_threads. The old image is
+    //gone even if this fails, exec checks what it can with init_stack_size first
+    pub fn exec(& mut self, thread: &Thread, elf_data: &[u8], args: &[String], envs: &[String]) -> Result<(), Errno> {
         self.frame_recycle();
         self.areas = vec![];
         let pg_root = frame_alloc().unwrap();
         self.page_table = PageTable::new(pg_root);
-        self.load_trap_cxt_trampoline();
-        self.elf_parser(elf_data);
+        self.page_table.load_trampoline();
+        let mut thread_inner = thread.inner();
+        thread_inner.trap_context_ppn = self.map_trap_cxt(thread.tid);
+        thread_inner.signals.backup = None;
+        drop(thread_inner);
+        self.elf_parser(thread, elf_data, args, envs)?;
+        self.signals.exec();
+        self.mutex_list.clear();
+        self.semaphore_list.clear();
+        self.condvar_list.clear();
+        Ok(())
     }
 
-    pub fn clone(obj: &Self) -> Self {
+    //the address space without any threads, trap contexts are left to the caller.
+    //Only the stack of the forking thread is copied, the child has no other threads
+    pub fn clone(obj: &mut Self, tid: usize) -> Self {
         let mut pid: usize;
         unsafe {
             pid = PID_ALLOCATOR.lock().alloc().unwrap();
         }
-        KERNEL_SPACE.lock().kernel_stack_apply(pid);
         let pg_root = frame_alloc().unwrap();
         let mut this = Process {
             pid,
             exit_code: 0,
-            context: (Context::goto_trap_return(kernel_stack_top(pid))),
-            status: Ready,
+            zombie: false,
             page_table: (PageTable::new(pg_root)),
             areas: vec![],
             parent: None,
             children: vec![],
-            trap_context_ppn: 0,
+            threads: vec![],
+            tid_allocator: RecycleCounter::new(MAX_THREADS),
+            heap_bottom: obj.heap_bottom,
+            program_brk: obj.program_brk,
+            stack_guard: obj.stack_guard,
+            stack_rlimit: obj.stack_rlimit,
+            fd_table: obj.fd_table.clone(),
+            signals: obj.signals.fork(),
+            //ids from the parent mean nothing in the child
+            mutex_list: vec![],
+            semaphore_list: vec![],
+            condvar_list: vec![],
         };
         this.page_table.load_trampoline();
-        for area in obj.areas.iter() {
-            let mut cur_area = area.clone();
-            this.page_table.area_mapping(&mut cur_area);
-            this.areas.push(cur_area);
-            for vpn in area.start..area.end {
-                let src = obj.page_table.find_pte(vpn).unwrap().ppn();
-                let dst = this.page_table.find_pte(vpn).unwrap().ppn();
-                read_frame(dst).copy_from_slice(read_frame(src));
+        //trap contexts live above every user area, they are written by the
+        //kernel directly and never shared
+        let other_stacks: Vec<VirPageNum> = obj.threads.iter().flatten()
+            .filter(|t| t.tid != tid)
+            .filter_map(|t| t.inner().ustack)
+            .map(|(bottom, _)| floor(bottom))
+            .collect();
+        for area in obj.areas.iter().filter(|a| a.start < floor(USER_STACK_TOP) && !other_stacks.contains(&a.start)) {
+            //share frames read only on both sides, the first store copies (see cow_fault)
+            let perm = area.map_perm & !MAP_PERM_W;
+            for (vpn, ppn) in area.frame_mapping.iter() {
+                frame_share(*ppn);
+                this.page_table.map(*vpn, *ppn, perm);
+                obj.page_table.remap(*vpn, *ppn, perm);
             }
+            this.areas.push(area.clone());
         }
-        this.trap_context_ppn = this.page_table.find_pte(addr_to_page_num(TRAP_CONTEXT)).unwrap().ppn();
-        let trap_context = this.get_trap_cxt();
-        trap_context.kernel_sp = kernel_stack_top(pid);
+        //the parent must not keep writing through a cached writable entry
+        obj.flush_tlb();
         this
     }
 
+    //returns false if the fault is a real segfault
+    pub fn page_fault(&mut self, vpn: VirPageNum, write: bool) -> bool {
+        if self.in_stack_range(vpn) {
+            return self.grow_stack(vpn);
+        }
+        let area = match self.areas.iter_mut().find(|a| a.contains(vpn)) {
+            Some(area) => area,
+            None => return false,
+        };
+        if write && area.map_perm & MAP_PERM_W == 0 || !write && area.map_perm & (MAP_PERM_R | MAP_PERM_X) == 0 {
+            return false;
+        }
+        if !area.frame_mapping.contains_key(&vpn) {
+            //reserved but never touched, back it with a zeroed frame
+            return self.page_table.page_mapping(area, vpn);
+        }
+        if write {
+            self.cow_fault(vpn)
+        } else {
+            false
+        }
+    }
+
+    //pages the main stack may still grow into, between its bottom and the
+    //rlimit and not taken by another area
+    pub fn in_stack_range(&self, vpn: VirPageNum) -> bool {
+        let limit = floor(USER_STACK_TOP.saturating_sub(self.stack_rlimit.cur));
+        limit <= vpn && vpn <= self.stack_guard && !self.areas.iter().any(|a| a.contains(vpn))
+    }
+
+    //move the stack bottom down to vpn and the guard page right under it,
+    //the pages skipped on the way are backed on first touch
+    fn grow_stack(&mut self, vpn: VirPageNum) -> bool {
+        let old_bottom = self.stack_guard + 1;
+        let new_guard = match vpn.checked_sub(1) {
+            Some(new_guard) => new_guard,
+            None => return false,
+        };
+        if self.areas.iter().any(|a| a.start < old_bottom && new_guard < a.end) {
+            return false;
+        }
+        let stack = match self.areas.iter_mut().find(|a| a.start == old_bottom) {
+            Some(stack) => stack,
+            None => return false,
+        };
+        stack.start = vpn;
+        if !self.page_table.page_mapping(stack, vpn) {
+            stack.start = old_bottom;
+            return false;
+        }
+        self.stack_guard = new_guard;
+        true
+    }
+
+    //store fault on a page shared by fork, returns false if it is a real fault
+    pub fn cow_fault(&mut self, vpn: VirPageNum) -> bool {
+        let area = match self.areas.iter_mut().find(|a| a.contains(vpn)) {
+            Some(area) => area,
+            None => return false,
+        };
+        if area.map_perm & MAP_PERM_W == 0 {
+            return false;
+        }
+        let ppn = match area.frame_mapping.get(&vpn) {
+            Some(ppn) => *ppn,
+            None => return false,
+        };
+        if self.page_table.find_pte(vpn).unwrap().is_writable() {
+            return false;
+        }
+        if frame_ref_count(ppn) == 1 {
+            //the other side already copied or exited
+            self.page_table.remap(vpn, ppn, area.map_perm);
+            self.flush_tlb();
+        } else {
+            let frame = match frame_alloc() {
+                Some(frame) => frame,
+                None => return false,
+            };
+            read_frame(frame).copy_from_slice(read_frame(ppn));
+            self.page_table.remap(vpn, frame, area.map_perm);
+            area.frame_mapping.insert(vpn, frame);
+            self.flush_tlb();
+            frame_dealloc(ppn);
+        }
+        true
+    }
+
+    //the kernel accesses user memory through physical addresses, which skips both
+    //lazy backing and the read only cow mapping, so fault the pages in by hand first
+    pub fn prepare_user_range(&mut self, start: VirAddr, len: usize, write: bool) -> bool {
+        for vpn in floor(start)..ceiling(start + len) {
+            let backed = self.areas.iter().any(|a| a.contains(vpn) && a.frame_mapping.contains_key(&vpn));
+            if !backed || write {
+                let writable = backed && self.page_table.find_pte(vpn).unwrap().is_writable();
+                if !writable && !self.page_fault(vpn, write) {
+                    return false;
+                }
+            }
+        }
+        true
+    }
+
+    //move the program break, 0 queries it. Pages are backed on first touch
+    //and given back when the heap shrinks. The heap is whatever areas lie in
+    //[heap_bottom, program_brk), mprotect and munmap may have split it
+    pub fn brk(&mut self, new_brk: usize) -> isize {
+        if new_brk == 0 {
+            return self.program_brk as isize;
+        }
+        if new_brk < self.heap_bottom {
+            return Errno::ENOMEM.into();
+        }
+        let heap_start = floor(self.heap_bottom);
+        let (old_end, new_end) = (ceiling(self.program_brk), ceiling(new_brk));
+        if new_end > old_end {
+            let collides = self.areas.iter().any(|a| a.start < new_end && old_end < a.end)
+                || (old_end..new_end).contains(&self.stack_guard);
+            if collides {
+                return Errno::ENOMEM.into();
+            }
+            //extend the top of the heap if it is still plain heap, else add a piece
+            match self.areas.iter_mut().find(|a| heap_start <= a.start && a.end == old_end && a.map_perm == HEAP_PERM) {
+                Some(top) => top.end = new_end,
+                None => self.areas.push(MapArea::new(page_num_to_addr(old_end), page_num_to_addr(new_end), Framed, HEAP_PERM)),
+            }
+        } else if new_end < old_end {
+            self.split_areas_at(new_end);
+            let page_table = &mut self.page_table;
+            let mut freed = vec![];
+            self.areas.retain(|area| {
+                let above = area.start < area.end && new_end <= area.start && area.end <= old_end;
+                if above {
+                    for (vpn, ppn) in area.frame_mapping.iter() {
+                        page_table.unmap(*vpn);
+                        freed.push(*ppn);
+                    }
+                }
+                !above
+            });
+            self.flush_tlb();
+            freed.into_iter().for_each(frame_dealloc);
+        }
+        self.program_brk = new_brk;
+        new_brk as isize
+    }
+
+    //split areas so that none crosses the page boundary at
+    fn split_areas_at(&mut self, at: VirPageNum) {
+        if let Some(area) = self.areas.iter_mut().find(|a| a.start < at && at < a.end) {
+            let upper = area.split_off(at);
+            self.areas.push(upper);
+        }
+    }
+
+    //anonymous zeroed memory at [start, start + len), backed on first touch
+    pub fn mmap(&mut self, start: VirAddr, len: usize, prot: usize) -> isize {
+        let map_perm = match prot_to_perm(prot) {
+            Some(map_perm) => map_perm,
+            None => return Errno::EINVAL.into(),
+        };
+        if start % PAGE_SIZE != 0 || len == 0 || start.checked_add(len).map_or(true, |end| end > USER_STACK_TOP) {
+            return Errno::EINVAL.into();
+        }
+        let (start_vpn, end_vpn) = (floor(start), ceiling(start + len));
+        if self.areas.iter().any(|a| a.start < end_vpn && start_vpn < a.end)
+            || (start_vpn..end_vpn).contains(&self.stack_guard) {
+            return Errno::EEXIST.into();
+        }
+        self.areas.push(MapArea::new(start, start + len, Framed, map_perm));
+        start as isize
+    }
+
+    //every page of [start, start + len) must be mapped
+    pub fn munmap(&mut self, start: VirAddr, len: usize) -> isize {
+        if start % PAGE_SIZE != 0 || len == 0 || start.checked_add(len).map_or(true, |end| end > USER_STACK_TOP) {
+            return Errno::EINVAL.into();
+        }
+        let (start_vpn, end_vpn) = (floor(start), ceiling(start + len));
+        if !(start_vpn..end_vpn).all(|vpn| self.areas.iter().any(|a| a.contains(vpn))) {
+            return Errno::EINVAL.into();
+        }
+        self.split_areas_at(start_vpn);
+        self.split_areas_at(end_vpn);
+        let page_table = &mut self.page_table;
+        let mut freed = vec![];
+        self.areas.retain(|area| {
+            let inside = area.start < area.end && start_vpn <= area.start && area.end <= end_vpn;
+            if inside {
+                for (vpn, ppn) in area.frame_mapping.iter() {
+                    page_table.unmap(*vpn);
+                    freed.push(*ppn);
+                }
+            }
+            !inside
+        });
+        self.flush_tlb();
+        freed.into_iter().for_each(frame_dealloc);
+        0
+    }
+
+    //every page of [start, start + len) must be mapped. With PROT_NONE the pages
+    //keep their frames but lose their entries until access comes back
+    pub fn mprotect(&mut self, start: VirAddr, len: usize, prot: usize) -> isize {
+        let map_perm = match prot_to_perm(prot) {
+            Some(map_perm) => map_perm,
+            None => return Errno::EINVAL.into(),
+        };
+        if start % PAGE_SIZE != 0 || len == 0 || start.checked_add(len).map_or(true, |end| end > USER_STACK_TOP) {
+            return Errno::EINVAL.into();
+        }
+        let (start_vpn, end_vpn) = (floor(start), ceiling(start + len));
+        if !(start_vpn..end_vpn).all(|vpn| self.areas.iter().any(|a| a.contains(vpn))) {
+            return Errno::ENOMEM.into();
+        }
+        self.split_areas_at(start_vpn);
+        self.split_areas_at(end_vpn);
+        for area in self.areas.iter_mut().filter(|a| start_vpn <= a.start && a.end <= end_vpn) {
+            area.map_perm = map_perm;
+            for (vpn, ppn) in area.frame_mapping.iter() {
+                //pages still shared with a fork stay read only until cow_fault copies them
+                let perm = if frame_ref_count(*ppn) > 1 { map_perm & !MAP_PERM_W } else { map_perm };
+                self.page_table.remap(*vpn, *ppn, perm);
+            }
+        }
+        self.flush_tlb();
+        0
+    }
+
+    pub fn getrlimit(&self, resource: usize) -> Option<RLimit> {
+        match resource {
+            RLIMIT_STACK => Some(self.stack_rlimit),
+            _ => None,
+        }
+    }
+
+    //only the stack limit is supported, it is checked whenever the stack grows
+    pub fn setrlimit(&mut self, resource: usize, rlimit: RLimit) -> isize {
+        if rlimit.cur > rlimit.max {
+            return Errno::EINVAL.into();
+        }
+        match resource {
+            //only root could raise the hard limit
+            RLIMIT_STACK if rlimit.max > self.stack_rlimit.max => Errno::EPERM.into(),
+            RLIMIT_STACK => {
+                self.stack_rlimit = rlimit;
+                0
+            }
+            _ => Errno::EINVAL.into(),
+        }
+    }
+
+    //drop cached translations on every hart after the page table changed, before
+    //any frame they pointed to is freed or a downgrade is relied on
+    fn flush_tlb(&self) {
+        shootdown(self.page_table.token());
+    }
+
     pub fn activate(&self) {
         let satp = self.page_table.token();
         unsafe {
@@ -182,44 +605,80 @@ impl Process {
         }
     }
 
-    pub fn elf_parser(&mut self,elf_data:&[u8]){
-        let elf = xmas_elf::ElfFile::new(elf_data).unwrap();
-        let elf_header = elf.header;
-        let magic = elf_header.pt1.magic;
-        assert_eq!(magic, [0x7f, 0x45, 0x4c, 0x46], "invalid elf!");
-        let mut max_end_vpn: VirPageNum = 0;
+    //copy data into user memory at va, backing pages on the way
+    fn write_user(&mut self, va: VirAddr, data: &[u8]) -> Result<(), Errno> {
+        copy_to_user(self, va, data)
+    }
+
+    //copy nul terminated strings below top, returns their addresses and the new top
+    fn push_strings(&mut self, mut top: VirAddr, strings: &[String]) -> Result<(Vec<usize>, VirAddr), Errno> {
+        let mut ptrs: Vec<usize> = Vec::new();
+        for string in strings {
+            top -= string.len() + 1;
+            self.write_user(top, string.as_bytes())?;
+            self.write_user(top + string.len(), &[0])?;
+            ptrs.push(top);
+        }
+        Ok((ptrs, top))
+    }
+
+    //linux style initial stack, from the 16 byte aligned sp upwards:
+    //argc, argv[], NULL, envp[], NULL, auxv pairs, AT_NULL, then the
+    //AT_RANDOM bytes and the strings. Returns (sp, argv, envp)
+    fn push_init_stack(&mut self, stack_top: VirAddr, args: &[String], envs: &[String], auxv: &[(usize, usize)]) -> Result<(VirAddr, VirAddr, VirAddr), Errno> {
+        let (argv, top) = self.push_strings(stack_top, args)?;
+        let (envp, top) = self.push_strings(top, envs)?;
+        let random_ptr = (top - 16) & !0xf;
+        let mut seed = unsafe { get_time() } as u64 ^ ((self.pid as u64) << 32);
+        let mut random = [0u8; 16];
+        for chunk in random.chunks_mut(8) {
+            //splitmix64, good enough for stack canaries
+            seed = seed.wrapping_add(0x9e3779b97f4a7c15);
+            let mut z = seed;
+            z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+            chunk.copy_from_slice(&(z ^ (z >> 31)).to_ne_bytes());
+        }
+        self.write_user(random_ptr, &random)?;
+        let mut words = vec![args.len()];
+        words.extend(argv);
+        words.push(0);
+        let envp_offset = words.len();
+        words.extend(envp);
+        words.push(0);
+        for (key, value) in auxv {
+            words.push(*key);
+            words.push(*value);
+        }
+        words.push(AT_RANDOM);
+        words.push(random_ptr);
+        words.push(AT_NULL);
+        words.push(0);
+        let sp = (random_ptr - words.len() * size_of::<usize>()) & !0xf;
+        let bytes: Vec<u8> = words.iter().flat_map(|w| w.to_ne_bytes()).collect();
+        self.write_user(sp, &bytes)?;
+        Ok((sp, sp + size_of::<usize>(), sp + envp_offset * size_of::<usize>()))
+    }
+
+    pub fn elf_parser(&mut self,thread:&Thread,elf_data:&[u8],args:&[String],envs:&[String]) -> Result<(), Errno> {
+        let elf = parse_elf(elf_data).unwrap();
         //map app memory area
-        for i in 0..elf_header.pt2.ph_count() {
-            let ph = elf.program_header(i).unwrap();
-            if ph.get_type().unwrap() == xmas_elf::program::Type::Load {
-                let start_va: VirAddr = ph.virtual_addr() as VirAddr;
-                let end_va: VirAddr = (ph.virtual_addr() + ph.mem_size()) as VirAddr;
-                let mut map_perm = MAP_PERM_U;
-                let ph_flags = ph.flags();
-                if ph_flags.is_read() {
-                    map_perm |= MAP_PERM_R;
-                }
-                if ph_flags.is_write() {
-                    map_perm |= MAP_PERM_W;
-                }
-                if ph_flags.is_execute() {
-                    map_perm |= MAP_PERM_X;
-                }
-                let mut map_area = MapArea::new(
-                    start_va,
-                    end_va,
-                    Framed,
-                    map_perm
-                );
-                max_end_vpn = map_area.end;
-                self.page_table.area_mapping(&mut map_area);
-                self.area_loading(&mut map_area, Some(&elf.input[ph.offset() as usize..(ph.offset() + ph.file_size()) as usize]));
-                self.areas.push(map_area);
+        for segment in elf.segments {
+            let mut map_area = segment.area;
+            //only pages holding file data are mapped now, bss is backed on first touch
+            let data_end = map_area.start + ceiling(segment.data.len());
+            for vpn in map_area.start..data_end.min(map_area.end) {
+                self.page_table.page_mapping(&mut map_area, vpn);
             }
+            self.area_loading(&mut map_area, Some(segment.data));
+            self.areas.push(map_area);
         }
+        //empty heap right above the image, brk grows it
+        self.heap_bottom = heap_base(elf.max_end_vpn);
+        self.program_brk = self.heap_bottom;
+        self.areas.push(MapArea::new(self.heap_bottom, self.heap_bottom, Framed, HEAP_PERM));
         //map user_stack
-        let user_stack_bottom = page_num_to_addr(max_end_vpn) + PAGE_SIZE;
-        let user_stack_top = user_stack_bottom + USER_STACK_SIZE;
+        let (user_stack_bottom, user_stack_top) = user_stack_range(USER_STACK_SIZE);
         let mut user_stack_area = MapArea::new(
             user_stack_bottom,
             user_stack_top,
@@ -227,25 +686,52 @@ impl Process {
             MAP_PERM_U | MAP_PERM_R | MAP_PERM_W
         );
         println!("User stack range {:#x} to {:#x}",user_stack_bottom,user_stack_top);
-        self.page_table.area_mapping(&mut user_stack_area);
+        self.stack_guard = floor(user_stack_bottom) - 1;
         self.areas.push(user_stack_area);
-        let trap_cxt = self.get_trap_cxt();
+        let auxv: [(usize, usize); AUXV_ENTRIES] = [
+            (AT_PHDR, elf.phdr),
+            (AT_PHENT, elf.phent),
+            (AT_PHNUM, elf.phnum),
+            (AT_PAGESZ, PAGE_SIZE),
+            (AT_ENTRY, elf.entry),
+        ];
+        let (user_sp, argv, envp) = self.push_init_stack(user_stack_top, args, envs, &auxv)?;
+        let trap_cxt = thread.inner().get_trap_cxt();
         *trap_cxt = TrapContext::app_init_context(
-            elf.header.pt2.entry_point() as usize,
-            user_stack_top,
+            elf.entry,
+            user_sp,
             KERNEL_SPACE.lock().kernel_token(),
-            kernel_stack_top(self.pid),
+            kernel_stack_top(thread.kstack),
             trap_handler as usize,
         );
+        trap_cxt.x[10] = args.len();
+        trap_cxt.x[11] = argv;
+        trap_cxt.x[12] = envp;
+        Ok(())
     }
 }
 
+//auxv pairs elf_parser passes besides AT_RANDOM and AT_NULL
+const AUXV_ENTRIES: usize = 5;
+
+//worst case size of what push_init_stack puts on the stack, including
+//the alignment of the AT_RANDOM bytes and of sp
+pub fn init_stack_size(args: &[String], envs: &[String]) -> usize {
+    let strings: usize = args.iter().chain(envs).map(|s| s.len() + 1).sum();
+    let words = 1 + args.len() + 1 + envs.len() + 1 + 2 * (AUXV_ENTRIES + 2);
+    strings + 15 + 16 + words * size_of::<usize>() + 15
+}
+
 lazy_static! {
-    pub static ref INITPROC: Arc<ProcessWrapper> = Arc::new(ProcessWrapper::new(
-        Process::load_elf( get_app_data_by_name("initproc").unwrap())
-    ));
+    pub static ref INITPROC: Arc<ProcessWrapper> = {
+        let inode = open_file("initproc", O_RDONLY).unwrap();
+        let elf_data = inode.read_all();
+        Process::load_elf(elf_data.as_slice(), &[String::from("initproc")], &[String::from("PATH=/")])
+    };
 }
 pub fn add_initproc() {
-    SCHEDULER.lock().push_prc(INITPROC.clone());
+    insert_prc(&INITPROC);
+    let main_thread = INITPROC.inner().threads[0].clone().unwrap();
+    SCHEDULER.lock().push_thread(main_thread);
     println!("Initproc loaded");
 }
@@ -0,0 +1,60 @@
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use lazy_static::lazy_static;
+use crate::CPUS;
+use crate::process::context::Context;
+use crate::process::process::ProcessWrapper;
+use crate::process::thread::Thread;
+use crate::sync::cell::{Mutex, MutexGuard};
+use crate::utility::hart_id;
+
+//what one hart is doing, the ready queue in SCHEDULER is shared by all of them
+pub struct Processor {
+    cur_thread: Option<Arc<Thread>>,
+    //the hart's scheduling loop, threads switch back here
+    idle_cxt: Context,
+}
+
+impl Processor {
+    pub fn new() -> Self {
+        Processor {
+            cur_thread: None,
+            idle_cxt: Context::new(),
+        }
+    }
+
+    pub fn current_thread(&self) -> Option<Arc<Thread>> {
+        self.cur_thread.as_ref().map(Arc::clone)
+    }
+
+    pub fn set_current_thread(&mut self, thread: Arc<Thread>) {
+        self.cur_thread = Some(thread);
+    }
+
+    pub fn take_current_thread(&mut self) -> Option<Arc<Thread>> {
+        self.cur_thread.take()
+    }
+
+    //stays put, the processors live as long as the kernel
+    pub fn idle_cxt_ptr(&mut self) -> *mut Context {
+        &mut self.idle_cxt as *mut Context
+    }
+}
+
+lazy_static! {
+    static ref PROCESSORS: Vec<Mutex<Processor>> = (0..CPUS).map(|_| Mutex::new(Processor::new())).collect();
+}
+
+//the processor of the calling hart
+pub fn processor() -> MutexGuard<'static, Processor> {
+    PROCESSORS[hart_id()].lock()
+}
+
+pub fn current_thread() -> Option<Arc<Thread>> {
+    processor().current_thread()
+}
+
+//process of the current thread, alive for as long as one of its threads runs
+pub fn current_prc() -> Option<Arc<ProcessWrapper>> {
+    current_thread().and_then(|t| t.process.upgrade())
+}
@@ -1,27 +1,48 @@
 use alloc::collections::VecDeque;
+use core::arch::asm;
+use riscv::register::sip;
 use alloc::sync::Arc;
 use alloc::vec;
 use alloc::vec::Vec;
 use core::borrow::Borrow;
+use core::hint::spin_loop;
+use core::mem::size_of;
 use core::ops::Deref;
+use core::sync::atomic::Ordering;
 
 use lazy_static::lazy_static;
+use lose_core::elf::parse_elf;
 use xmas_elf::dynamic::Tag::Null;
+use crate::fs::inode::open_file;
+use crate::fs::pipe::make_pipe;
+use crate::fs::{File, O_RDONLY, UserBuffer};
+use crate::utility::timer::{add_timer, check_timers, get_time, remove_timer, set_next_trigger, TimeSpec, NSEC_PER_SEC};
+use crate::io::external_interrupt;
 use crate::io::print;
 
-use crate::loader::{get_app_data, get_app_data_by_name};
+use crate::mm::{floor, USER_STACK_SIZE};
+use crate::mm::kernel_space::{KERNEL_SPACE, kernel_stack_top};
 use crate::mm::pagetable::PageTable;
+use crate::mm::user_ptr::{read_user_str, read_user_str_array, UserPtr, UserSlice};
 use crate::println;
 use crate::process::context::{Context, cxt_switch};
-use crate::process::process::{INITPROC, Process, ProcessStatus, ProcessWrapper};
-use crate::process::process::ProcessStatus::Dead;
+use crate::process::process::{init_stack_size, INITPROC, insert_prc, pid2prc, Process, ProcessWrapper, remove_prc, RLimit};
+use crate::process::processor::{current_prc, current_thread, processor};
+use crate::process::signal::{MAX_SIG, sig_bit, SIG_BLOCK, SIG_SETMASK, SIG_UNBLOCK, sig_uncatchable, SIGKILL, SignalAction, SignalDelivery};
+use crate::process::thread::{Thread, ThreadStatus};
 use crate::sync::cell::Mutex;
+use crate::sync::condvar::Condvar;
+use crate::sync::futex::{FUTEX_PRIVATE_FLAG, FUTEX_WAIT, FUTEX_WAKE, futex_remove_dead, futex_wait, futex_wake};
+use crate::sync::mutex::UserMutex;
+use crate::sync::semaphore::Semaphore;
+use crate::syscall::errno::Errno;
 use crate::trap::trap_context::TrapContext;
+use crate::trap::trap_handler;
+use crate::utility::hart_id;
 
+//threads ready to run, shared by every hart. What a hart runs is in its Processor
 pub struct Scheduler {
-    available_queue: Vec<Arc<ProcessWrapper>>,
-    cur_prc: Option<Arc<ProcessWrapper>>,
-    scheduler_cxt: Context,
+    available_queue: Vec<Arc<Thread>>,
 }
 
 impl Scheduler {
@@ -29,41 +50,55 @@ impl Scheduler {
         println!("Scheduler online");
         Scheduler {
             available_queue: Vec::new(),
-            cur_prc: None,
-            scheduler_cxt: Context::new(),
         }
     }
 
-    pub fn take_current_prc(&mut self) -> Option<Arc<ProcessWrapper>> {
-        self.cur_prc.take()
+    pub fn push_thread(&mut self, thread: Arc<Thread>) {
+        self.available_queue.push(thread);
     }
 
-    pub fn current_prc(&self) -> Option<Arc<ProcessWrapper>> {
-        self.cur_prc.as_ref().map(Arc::clone)
+    pub fn pop(&mut self) -> Option<Arc<Thread>> {
+        if self.available_queue.is_empty() {
+            None
+        } else {
+            Some(self.available_queue.remove(0))
+        }
     }
 
-    pub fn push_prc(&mut self, prc: Arc<ProcessWrapper>) {
-        self.available_queue.push(prc);
+    //make a blocked thread runnable again, false if it was not blocked
+    pub fn wake(thread: Arc<Thread>) -> bool {
+        let mut thread_inner = thread.inner();
+        if thread_inner.status != ThreadStatus::Blocked {
+            return false;
+        }
+        thread_inner.status = ThreadStatus::Ready;
+        drop(thread_inner);
+        SCHEDULER.lock().push_thread(thread);
+        true
     }
 
-    pub fn pop(&mut self) -> Option<Arc<ProcessWrapper>> {
-        Some(self.available_queue.remove(0))
+    pub fn get_cur_pid() -> usize {
+        current_prc().unwrap().pid
     }
 
-    pub fn get_cur_pid() -> usize {
-        SCHEDULER.lock().current_prc().unwrap().inner().pid
+    pub fn get_cur_tid() -> usize {
+        current_thread().unwrap().tid
     }
 
     pub fn get_cur_token() -> usize {
-        SCHEDULER.lock().current_prc().unwrap().inner().page_table.token()
+        current_prc().unwrap().inner().page_table.token()
+    }
+
+    pub fn get_cur_file(fd: usize) -> Option<Arc<dyn File>> {
+        current_prc().unwrap().inner().get_file(fd)
     }
 
     // pub fn get_cur_pg_table() -> & 'static PageTable{
-    //     & SCHEDULER.lock().current_prc().unwrap().page_table
+    //     & current_prc().unwrap().page_table
     // }
 
     pub fn get_cur_trap_cxt() -> &'static mut TrapContext {
-        SCHEDULER.lock().current_prc().unwrap().inner().get_trap_cxt()
+        current_thread().unwrap().inner().get_trap_cxt()
     }
 }
 
@@ -71,51 +106,171 @@ lazy_static! {
     pub static ref SCHEDULER: Mutex<Scheduler> =unsafe { Mutex::new(Scheduler::new()) };
 }
 
-pub fn run() {
-    println!("Begin scheduling!");
+//the scheduling loop of one hart
+pub fn run() -> ! {
+    println!("Hart {} begins scheduling!", hart_id());
     loop {
-        let mut scheduler = SCHEDULER.lock();
-        if let Some(prc) = scheduler.pop() {
-            let mut prc_inner = prc.inner();
-            let scheduler_cxt_ptr = &mut scheduler.scheduler_cxt as *mut Context;
-            let next_cxt_ptr = &prc_inner.context as *const Context;
-            // println!("next prc {}", prc.pid);
-            prc_inner.status = ProcessStatus::Running;
-            drop(prc_inner);
-            scheduler.cur_prc = Some(prc);
-            drop(scheduler);
+        let thread = SCHEDULER.lock().pop();
+        if let Some(thread) = thread {
+            //the hart it ran on last may not have left its kernel stack yet
+            while thread.on_cpu.load(Ordering::Acquire) {
+                spin_loop();
+            }
+            let mut thread_inner = thread.inner();
+            //killed along with its process while it was waiting
+            if thread_inner.status == ThreadStatus::Dead {
+                continue;
+            }
+            thread_inner.status = ThreadStatus::Running;
+            //set under the thread lock, so whoever marks it Dead sees it running
+            thread.on_cpu.store(true, Ordering::Relaxed);
+            let next_cxt_ptr = &thread_inner.context as *const Context;
+            drop(thread_inner);
+            let idle_cxt_ptr = {
+                let mut processor = processor();
+                processor.set_current_thread(thread);
+                processor.idle_cxt_ptr()
+            };
             unsafe {
-                cxt_switch(scheduler_cxt_ptr, next_cxt_ptr);
+                cxt_switch(idle_cxt_ptr, next_cxt_ptr);
             }
+            //off its kernel stack now, any hart may pick it up again
+            let prev = processor().take_current_thread().unwrap();
+            prev.on_cpu.store(false, Ordering::Release);
+        } else {
+            idle();
+        }
+    }
+}
+
+//nothing to run, interrupts are off in the kernel so wait for one
+//to become pending and handle it by hand
+fn idle() {
+    unsafe {
+        asm!("wfi");
+    }
+    let sip = sip::read();
+    if sip.stimer() {
+        set_next_trigger();
+        check_timers();
+    }
+    if sip.sext() {
+        external_interrupt();
+    }
+    //tlb shootdowns wake idle harts too, they flush before running a thread anyway
+    if sip.ssoft() {
+        unsafe {
+            sip::clear_ssoft();
         }
     }
 }
 
 impl Scheduler {
-    pub fn kernel_yield() {
-        let mut scheduler = SCHEDULER.lock();
-        let cur_prc = scheduler.current_prc().unwrap();
-        let mut cur_prc_inner = cur_prc.inner();
-        let cur_cxt_ptr = &mut cur_prc_inner.context as *mut Context;
-        let scheduler_cxt_ptr = &scheduler.scheduler_cxt as *const Context;
-        cur_prc_inner.status = ProcessStatus::Ready;
-        // println!("Process {} yield.", cur_prc_inner.pid);
-        drop(cur_prc_inner);
-        scheduler.push_prc(cur_prc);
-        // println!("[After yield] Have {} process in scheduler the first is {}.", scheduler.available_queue.len(), scheduler.available_queue.first().unwrap().pid);
-        drop(scheduler);
+    //status of a thread that is not Dead, false leaves a killed thread alone
+    pub fn set_alive_status(thread: &Thread, status: ThreadStatus) -> bool {
+        let mut thread_inner = thread.inner();
+        if thread_inner.status == ThreadStatus::Dead {
+            return false;
+        }
+        thread_inner.status = status;
+        true
+    }
+
+    //back to the scheduling loop of this hart. The caller has set the status
+    //and queued the thread wherever it waits, a Dead one never comes back
+    pub fn switch_away(cur_thread: Arc<Thread>) {
+        let cur_cxt_ptr = &mut cur_thread.inner().context as *mut Context;
+        let idle_cxt_ptr = processor().idle_cxt_ptr();
+        drop(cur_thread);
         unsafe {
-            cxt_switch(cur_cxt_ptr, scheduler_cxt_ptr);
+            cxt_switch(cur_cxt_ptr, idle_cxt_ptr);
+        }
+    }
+
+    pub fn kernel_yield() {
+        let cur_thread = current_thread().unwrap();
+        if Scheduler::set_alive_status(&cur_thread, ThreadStatus::Ready) {
+            SCHEDULER.lock().push_thread(cur_thread.clone());
         }
+        Scheduler::switch_away(cur_thread);
     }
 
+    //a thread killed by another one leaves at its next trap, before it
+    //touches the memory of its process again
+    pub fn leave_if_dead() {
+        let cur_thread = current_thread().unwrap();
+        let dead = cur_thread.inner().status == ThreadStatus::Dead;
+        if dead {
+            Scheduler::switch_away(cur_thread);
+        }
+    }
+
+    //spin until none of threads runs on a hart any more
+    fn wait_off_cpu(threads: &[Arc<Thread>]) {
+        while threads.iter().any(|t| t.on_cpu.load(Ordering::Acquire)) {
+            spin_loop();
+        }
+    }
+
+    //the main thread exiting takes the whole process with it
     pub fn kernel_exit(exit_code: i32) {
-        let scheduler = SCHEDULER.lock();
-        let cur_prc = scheduler.current_prc().unwrap();
+        let tid = Scheduler::get_cur_tid();
+        if tid == 0 {
+            Scheduler::exit_process(exit_code);
+        } else {
+            Scheduler::exit_thread(exit_code);
+        }
+    }
+
+    fn exit_thread(exit_code: i32) {
+        let cur_thread = current_thread().unwrap();
+        let cur_prc = cur_thread.process.upgrade().unwrap();
         let mut cur_prc_inner = cur_prc.inner();
-        let pid = cur_prc_inner.pid;
-        cur_prc_inner.status = ProcessStatus::Dead;
+        let mut cur_thread_inner = cur_thread.inner();
+        //the process is going away already, its memory with it
+        if cur_thread_inner.status == ThreadStatus::Dead {
+            drop(cur_thread_inner);
+            drop(cur_prc_inner);
+            Scheduler::switch_away(cur_thread);
+            return;
+        }
+        cur_thread_inner.status = ThreadStatus::Dead;
+        cur_thread_inner.exit_code = Some(exit_code);
+        cur_prc_inner.release_thread_res(cur_thread.tid, cur_thread_inner.ustack.take());
+        drop(cur_thread_inner);
+        drop(cur_prc_inner);
+        cur_prc.thread_exit.wake_all();
+        drop(cur_prc);
+        Scheduler::switch_away(cur_thread);
+    }
+
+    //also ends every other thread, wherever it is. Whichever thread takes the
+    //process lock first to exit or exec kills the others, they find themselves
+    //Dead and leave instead of waiting on it in turn
+    fn exit_process(exit_code: i32) {
+        let cur_thread = current_thread().unwrap();
+        let cur_prc = cur_thread.process.upgrade().unwrap();
+        let mut cur_prc_inner = cur_prc.inner();
+        let dead = cur_thread.inner().status == ThreadStatus::Dead;
+        if dead {
+            drop(cur_prc_inner);
+            drop(cur_prc);
+            Scheduler::switch_away(cur_thread);
+            return;
+        }
+        remove_prc(cur_prc_inner.pid);
+        cur_prc_inner.zombie = true;
         cur_prc_inner.exit_code = exit_code;
+        let others = cur_prc_inner.kill_other_threads(cur_thread.tid);
+        cur_thread.inner().status = ThreadStatus::Dead;
+        drop(cur_prc_inner);
+        futex_remove_dead();
+        //they may still be using the memory freed below
+        Scheduler::wait_off_cpu(&others);
+        drop(others);
+        let mut cur_prc_inner = cur_prc.inner();
+        cur_prc_inner.threads.clear();
+        let adopted = !cur_prc_inner.children.is_empty();
         {
             let mut initproc_inner = INITPROC.inner();
             for child in cur_prc_inner.children.iter() {
@@ -124,77 +279,666 @@ impl Scheduler {
             }
         }
         cur_prc_inner.children.clear();
+        cur_prc_inner.fd_table.clear();
         cur_prc_inner.frame_recycle();
-        let scheduler_cxt_ptr = &scheduler.scheduler_cxt as *const Context;
-        drop(scheduler);
+        let parent = cur_prc_inner.parent.as_ref().and_then(|p| p.upgrade());
         drop(cur_prc_inner);
-        let null_cxt = &mut Context::new();
-        unsafe {
-            cxt_switch(null_cxt, scheduler_cxt_ptr);
+        drop(cur_prc);
+        if let Some(parent) = parent {
+            //a parent in waitpid holds its lock until it is queued, so
+            //past this it has either seen the exit or is waiting for the wake
+            drop(parent.inner());
+            parent.child_exit.wake_all();
+        }
+        //adopted children may already be dead
+        if adopted {
+            INITPROC.child_exit.wake_all();
         }
+        Scheduler::switch_away(cur_thread);
     }
 
     pub fn kernel_waitpid(pid: isize, exit_code_ptr: *mut i32) -> isize {
-        let scheduler = SCHEDULER.lock();
-        let cur_prc = scheduler.current_prc().unwrap();
-        let mut cur_prc_inner = cur_prc.inner();
+        let cur_prc = current_prc().unwrap();
+        loop {
+            let mut cur_prc_inner = cur_prc.inner();
+            if let Some(result) = Scheduler::try_waitpid(&mut cur_prc_inner, pid, exit_code_ptr) {
+                return result;
+            }
+            if !cur_prc.child_exit.wait_then(|| drop(cur_prc_inner)) {
+                return Errno::EINTR.into();
+            }
+        }
+    }
+
+    //None while the child is still running
+    fn try_waitpid(cur_prc_inner: &mut Process, pid: isize, exit_code_ptr: *mut i32) -> Option<isize> {
         // println!("Process {} waitpid.", cur_prc_inner.pid);
         if cur_prc_inner.children.is_empty() ||
             pid != -1 && !cur_prc_inner.children.iter()
                 .any(|p| pid == -1 || pid as usize == p.pid) {
-            return -1;
+            return Some(Errno::ECHILD.into());
         }
         let pair = cur_prc_inner.children.iter().enumerate().find(
             |(_, p)| {
-                (p.pid == pid as usize || pid == -1) && p.inner().status == Dead
+                (p.pid == pid as usize || pid == -1) && p.inner().zombie
             }
         );
         if let Some((idx, _)) = pair {
+            let exit_code = cur_prc_inner.children[idx].inner().exit_code;
+            //a bad pointer leaves the child to be reaped later, a null one skips the code
+            let exit_code_ptr = UserPtr::new(exit_code_ptr as *const i32);
+            if !exit_code_ptr.is_null() {
+                if let Err(errno) = exit_code_ptr.write(cur_prc_inner, exit_code) {
+                    return Some(errno.into());
+                }
+            }
             let child = cur_prc_inner.children.remove(idx);
-            let child_inner = child.inner();
             // assert_eq!(Arc::strong_count(&child), 1);
-            let found_pid = child_inner.pid;
-            let exit_code = child_inner.exit_code;
-            //todo can or not use pagetable of current process
-            let exit_code_pa = cur_prc_inner.page_table.translate_va(exit_code_ptr as usize).unwrap() as *mut i32;
-            unsafe {
-                *exit_code_pa = exit_code
+            let found_pid = child.pid;
+            return Some(found_pid as isize);
+        }
+        None
+    }
+
+    //a new thread of the current process starting at entry with arg in a0,
+    //on a user stack of its own. Returns its tid
+    pub fn kernel_thread_create(entry: usize, arg: usize) -> isize {
+        let cur_thread = current_thread().unwrap();
+        let cur_prc = cur_thread.process.upgrade().unwrap();
+        let mut cur_prc_inner = cur_prc.inner();
+        //a killed caller must leave nothing behind in a process that is going away
+        if cur_thread.inner().status == ThreadStatus::Dead {
+            return Errno::ESRCH.into();
+        }
+        let sig_mask = cur_thread.inner().signals.mask;
+        let thread = match Thread::new(&cur_prc, &mut cur_prc_inner, true, sig_mask) {
+            Ok(thread) => thread,
+            Err(errno) => return errno.into(),
+        };
+        drop(cur_prc_inner);
+        let mut thread_inner = thread.inner();
+        let (_, ustack_top) = thread_inner.ustack.unwrap();
+        let trap_cxt = thread_inner.get_trap_cxt();
+        *trap_cxt = TrapContext::app_init_context(
+            entry,
+            ustack_top,
+            KERNEL_SPACE.lock().kernel_token(),
+            kernel_stack_top(thread.kstack),
+            trap_handler as usize,
+        );
+        trap_cxt.x[10] = arg;
+        drop(thread_inner);
+        let tid = thread.tid;
+        SCHEDULER.lock().push_thread(thread);
+        tid as isize
+    }
+
+    pub fn kernel_gettid() -> usize {
+        Scheduler::get_cur_tid()
+    }
+
+    //wait for thread tid of the current process to exit and reap it,
+    //returns its exit code
+    pub fn kernel_waittid(tid: usize) -> isize {
+        let cur_thread = current_thread().unwrap();
+        if cur_thread.tid == tid {
+            return Errno::EDEADLK.into();
+        }
+        let cur_prc = cur_thread.process.upgrade().unwrap();
+        loop {
+            let mut cur_prc_inner = cur_prc.inner();
+            if let Some(result) = Scheduler::try_waittid(&mut cur_prc_inner, tid) {
+                return result;
+            }
+            //exit_thread sets the exit code under this lock
+            if !cur_prc.thread_exit.wait_then(|| drop(cur_prc_inner)) {
+                return Errno::EINTR.into();
+            }
+        }
+    }
+
+    //None while the thread is still running
+    fn try_waittid(cur_prc_inner: &mut Process, tid: usize) -> Option<isize> {
+        let thread = match cur_prc_inner.threads.get(tid) {
+            Some(Some(thread)) => thread.clone(),
+            _ => return Some(Errno::ESRCH.into()),
+        };
+        let exit_code = thread.inner().exit_code?;
+        cur_prc_inner.threads[tid] = None;
+        cur_prc_inner.tid_allocator.dealloc(tid);
+        Some(exit_code as isize)
+    }
+
+    pub fn kernel_mutex_create() -> isize {
+        let cur_prc = current_prc().unwrap();
+        let mut cur_prc_inner = cur_prc.inner();
+        cur_prc_inner.mutex_list.push(Arc::new(UserMutex::new()));
+        (cur_prc_inner.mutex_list.len() - 1) as isize
+    }
+
+    pub fn kernel_mutex_lock(id: usize) -> isize {
+        let cur_thread = current_thread().unwrap();
+        let cur_prc = cur_thread.process.upgrade().unwrap();
+        let mutex = cur_prc.inner().mutex_list.get(id).cloned();
+        match mutex {
+            Some(mutex) if mutex.lock(cur_thread.tid) => 0,
+            Some(_) => Errno::EINTR.into(),
+            None => Errno::EINVAL.into(),
+        }
+    }
+
+    //only the thread holding it may unlock it
+    pub fn kernel_mutex_unlock(id: usize) -> isize {
+        let cur_thread = current_thread().unwrap();
+        let cur_prc = cur_thread.process.upgrade().unwrap();
+        let mutex = cur_prc.inner().mutex_list.get(id).cloned();
+        match mutex {
+            Some(mutex) if mutex.unlock(cur_thread.tid) => 0,
+            Some(_) => Errno::EPERM.into(),
+            None => Errno::EINVAL.into(),
+        }
+    }
+
+    pub fn kernel_semaphore_create(count: usize) -> isize {
+        let cur_prc = current_prc().unwrap();
+        let mut cur_prc_inner = cur_prc.inner();
+        cur_prc_inner.semaphore_list.push(Arc::new(Semaphore::new(count)));
+        (cur_prc_inner.semaphore_list.len() - 1) as isize
+    }
+
+    pub fn kernel_semaphore_up(id: usize) -> isize {
+        let cur_prc = current_prc().unwrap();
+        let semaphore = cur_prc.inner().semaphore_list.get(id).cloned();
+        match semaphore {
+            Some(semaphore) => {
+                semaphore.up();
+                0
+            }
+            None => Errno::EINVAL.into(),
+        }
+    }
+
+    pub fn kernel_semaphore_down(id: usize) -> isize {
+        let cur_prc = current_prc().unwrap();
+        let semaphore = cur_prc.inner().semaphore_list.get(id).cloned();
+        match semaphore {
+            Some(semaphore) if semaphore.down() => 0,
+            Some(_) => Errno::EINTR.into(),
+            None => Errno::EINVAL.into(),
+        }
+    }
+
+    pub fn kernel_condvar_create() -> isize {
+        let cur_prc = current_prc().unwrap();
+        let mut cur_prc_inner = cur_prc.inner();
+        cur_prc_inner.condvar_list.push(Arc::new(Condvar::new()));
+        (cur_prc_inner.condvar_list.len() - 1) as isize
+    }
+
+    pub fn kernel_condvar_signal(id: usize) -> isize {
+        let cur_prc = current_prc().unwrap();
+        let condvar = cur_prc.inner().condvar_list.get(id).cloned();
+        match condvar {
+            Some(condvar) => {
+                condvar.signal();
+                0
             }
-            return found_pid as isize;
+            None => Errno::EINVAL.into(),
         }
-        -2
+    }
+
+    //mutex_id must be locked by the caller, it is locked again on return
+    pub fn kernel_condvar_wait(id: usize, mutex_id: usize) -> isize {
+        let cur_thread = current_thread().unwrap();
+        let cur_prc = cur_thread.process.upgrade().unwrap();
+        let cur_prc_inner = cur_prc.inner();
+        let condvar = cur_prc_inner.condvar_list.get(id).cloned();
+        let mutex = cur_prc_inner.mutex_list.get(mutex_id).cloned();
+        drop(cur_prc_inner);
+        match (condvar, mutex) {
+            //the mutex is not held again after EINTR
+            (Some(condvar), Some(mutex)) => match condvar.wait(&mutex, cur_thread.tid) {
+                Ok(()) => 0,
+                Err(errno) => errno.into(),
+            },
+            _ => Errno::EINVAL.into(),
+        }
+    }
+
+    //FUTEX_WAIT blocks while the u32 at addr still holds val, FUTEX_WAKE wakes up
+    //to val waiters. Waiters are keyed by the physical address of the word
+    pub fn kernel_futex(addr: usize, op: usize, val: usize) -> isize {
+        if addr % size_of::<u32>() != 0 {
+            return Errno::EINVAL.into();
+        }
+        let cur_prc = current_prc().unwrap();
+        let mut cur_prc_inner = cur_prc.inner();
+        //asking for write access breaks cow sharing, so a forked child never
+        //ends up on the same key as its parent
+        let buffers = UserSlice::new(addr as *const u8, size_of::<u32>()).buffers(&mut cur_prc_inner, true);
+        if let Err(errno) = buffers {
+            return errno.into();
+        }
+        let key = cur_prc_inner.page_table.translate_va(addr).unwrap();
+        drop(cur_prc_inner);
+        match op & !FUTEX_PRIVATE_FLAG {
+            FUTEX_WAIT => match futex_wait(key, val as u32) {
+                Ok(()) => 0,
+                Err(errno) => errno.into(),
+            },
+            FUTEX_WAKE => futex_wake(key, val) as isize,
+            _ => Errno::ENOSYS.into(),
+        }
+    }
+
+    pub fn kernel_sleep(ticks: usize) -> isize {
+        let deadline = unsafe { get_time() }.saturating_add(ticks);
+        while unsafe { get_time() } < deadline {
+            let cur_thread = current_thread().unwrap();
+            if Scheduler::set_alive_status(&cur_thread, ThreadStatus::Blocked) {
+                add_timer(deadline, cur_thread.clone());
+            }
+            //see WaitQueue::wait_then
+            if Scheduler::interrupted() {
+                Scheduler::wake(cur_thread.clone());
+            }
+            Scheduler::switch_away(cur_thread);
+            remove_timer(&current_thread().unwrap());
+            if Scheduler::interrupted() {
+                return Errno::EINTR.into();
+            }
+        }
+        0
+    }
+
+    pub fn kernel_nanosleep(req: *const TimeSpec) -> isize {
+        let req = match Scheduler::read_user(req) {
+            Ok(req) => req,
+            Err(errno) => return errno.into(),
+        };
+        if req.nsec >= NSEC_PER_SEC {
+            return Errno::EINVAL.into();
+        }
+        Scheduler::kernel_sleep(req.to_ticks())
+    }
+
+    pub fn kernel_page_fault(va: usize, write: bool) -> bool {
+        let cur_prc = current_prc().unwrap();
+        let result = cur_prc.inner().page_fault(floor(va), write);
+        result
+    }
+
+    //[ptr, ptr + len) of the current process split at page boundaries, EFAULT
+    //unless every page is accessible. It stays valid after the process lock is gone
+    pub fn user_buffer(ptr: *const u8, len: usize, write: bool) -> Result<UserBuffer, Errno> {
+        let cur_prc = current_prc().unwrap();
+        let mut cur_prc_inner = cur_prc.inner();
+        let result = UserSlice::new(ptr, len).buffers(&mut cur_prc_inner, write).map(UserBuffer::new);
+        drop(cur_prc_inner);
+        result
+    }
+
+    pub fn read_user<T: Copy>(ptr: *const T) -> Result<T, Errno> {
+        let cur_prc = current_prc().unwrap();
+        let result = UserPtr::new(ptr).read(&mut cur_prc.inner());
+        result
+    }
+
+    pub fn write_user<T: Copy>(ptr: *mut T, value: T) -> Result<(), Errno> {
+        let cur_prc = current_prc().unwrap();
+        let result = UserPtr::new(ptr).write(&mut cur_prc.inner(), value);
+        result
+    }
+
+    pub fn in_stack_guard(va: usize) -> bool {
+        let cur_prc = current_prc().unwrap();
+        let cur_prc_inner = cur_prc.inner();
+        let vpn = floor(va);
+        let result = cur_prc_inner.stack_guard == vpn || cur_prc_inner.in_stack_range(vpn);
+        drop(cur_prc_inner);
+        result
+    }
+
+    pub fn kernel_brk(new_brk: usize) -> isize {
+        let cur_prc = current_prc().unwrap();
+        let result = cur_prc.inner().brk(new_brk);
+        result
+    }
+
+    pub fn kernel_mmap(start: usize, len: usize, prot: usize) -> isize {
+        let cur_prc = current_prc().unwrap();
+        let result = cur_prc.inner().mmap(start, len, prot);
+        result
+    }
+
+    pub fn kernel_munmap(start: usize, len: usize) -> isize {
+        let cur_prc = current_prc().unwrap();
+        let result = cur_prc.inner().munmap(start, len);
+        result
+    }
+
+    pub fn kernel_mprotect(start: usize, len: usize, prot: usize) -> isize {
+        let cur_prc = current_prc().unwrap();
+        let result = cur_prc.inner().mprotect(start, len, prot);
+        result
     }
 
     pub fn kernel_getpid() -> usize {
         Scheduler::get_cur_pid()
     }
 
+    //the child gets a copy of the calling thread only, as its main thread
     pub fn kernel_fork() -> isize {
-        let mut scheduler = SCHEDULER.lock();
-        let cur_prc = scheduler.current_prc().unwrap();
+        let cur_thread = current_thread().unwrap();
+        let cur_prc = cur_thread.process.upgrade().unwrap();
         let mut cur_prc_inner = cur_prc.inner();
         println!("Process {} fork.", cur_prc_inner.pid);
-        let mut new_prc_inner = Process::clone(cur_prc_inner.borrow());
+        let mut new_prc_inner = Process::clone(&mut cur_prc_inner, cur_thread.tid);
         new_prc_inner.parent = Option::from(Arc::downgrade(&cur_prc));
-        let trap_cxt = new_prc_inner.get_trap_cxt();
-        trap_cxt.x[10] = 0;
         let new_prc = Arc::new(ProcessWrapper::new(new_prc_inner));
+        let mut new_prc_inner = new_prc.inner();
+        let sig_mask = cur_thread.inner().signals.mask;
+        let new_thread = Thread::new(&new_prc, &mut new_prc_inner, false, sig_mask).unwrap();
+        drop(new_prc_inner);
+        let trap_cxt = new_thread.inner().get_trap_cxt();
+        *trap_cxt = *cur_thread.inner().get_trap_cxt();
+        trap_cxt.kernel_sp = kernel_stack_top(new_thread.kstack);
+        trap_cxt.x[10] = 0;
         let new_pid = new_prc.pid;
-        cur_prc_inner.children.push(new_prc.clone());
-        scheduler.push_prc(new_prc);
+        insert_prc(&new_prc);
+        cur_prc_inner.children.push(new_prc);
+        drop(cur_prc_inner);
+        SCHEDULER.lock().push_thread(new_thread);
         new_pid as isize
     }
 
-    pub fn kernel_exec(path: *const u8) -> isize {
-        let mut scheduler = &SCHEDULER.lock();
-        let cur_prc = scheduler.current_prc().unwrap();
+    pub fn kernel_exec(path: *const u8, argv: *const usize, envp: *const usize) -> isize {
+        let cur_thread = current_thread().unwrap();
+        //the other threads would have to be stopped first
+        if cur_thread.tid != 0 {
+            return Errno::EINVAL.into();
+        }
+        let cur_prc = cur_thread.process.upgrade().unwrap();
+        let mut cur_prc_inner = cur_prc.inner();
+        let path = match read_user_str(&mut cur_prc_inner, path) {
+            Ok(path) => path,
+            Err(errno) => return errno.into(),
+        };
+        let args = match read_user_str_array(&mut cur_prc_inner, argv) {
+            Ok(args) => args,
+            Err(errno) => return errno.into(),
+        };
+        let envs = match read_user_str_array(&mut cur_prc_inner, envp) {
+            Ok(envs) => envs,
+            Err(errno) => return errno.into(),
+        };
+        if let Some(app_inode) = open_file(path.as_str(), O_RDONLY) {
+            let all_data = app_inode.read_all();
+            //check before the old image is torn down
+            if parse_elf(all_data.as_slice()).is_err() {
+                return Errno::ENOEXEC.into();
+            }
+            if init_stack_size(&args, &envs) > USER_STACK_SIZE {
+                return Errno::E2BIG.into();
+            }
+            //killed by an exit in another thread, it leaves in trap_return
+            if cur_thread.inner().status == ThreadStatus::Dead {
+                return Errno::ESRCH.into();
+            }
+            //the old image stays until no other thread runs on it
+            let others = cur_prc_inner.kill_other_threads(cur_thread.tid);
+            drop(cur_prc_inner);
+            futex_remove_dead();
+            Scheduler::wait_off_cpu(&others);
+            drop(others);
+            let mut cur_prc_inner = cur_prc.inner();
+            match cur_prc_inner.exec(&cur_thread, all_data.as_slice(), &args, &envs) {
+                Ok(()) => 0,
+                //no image left to return to
+                Err(errno) => {
+                    cur_prc_inner.signals.raise(SIGKILL);
+                    errno.into()
+                }
+            }
+        } else {
+            Errno::ENOENT.into()
+        }
+    }
+
+    pub fn kernel_open(path: *const u8, flags: u32) -> isize {
+        let cur_prc = current_prc().unwrap();
         let mut cur_prc_inner = cur_prc.inner();
-        let path = cur_prc_inner.page_table.translated_str(path);
-        if let Some(data) = get_app_data_by_name(path.as_str()) {
-            cur_prc_inner.exec(data);
-            0
+        let path = match read_user_str(&mut cur_prc_inner, path) {
+            Ok(path) => path,
+            Err(errno) => return errno.into(),
+        };
+        if let Some(inode) = open_file(path.as_str(), flags) {
+            match cur_prc_inner.alloc_fd(inode) {
+                Ok(fd) => fd as isize,
+                Err(errno) => errno.into(),
+            }
         } else {
-            -1
+            Errno::ENOENT.into()
+        }
+    }
+
+    pub fn kernel_close(fd: usize) -> isize {
+        let cur_prc = current_prc().unwrap();
+        let mut cur_prc_inner = cur_prc.inner();
+        if fd >= cur_prc_inner.fd_table.len() || cur_prc_inner.fd_table[fd].is_none() {
+            return Errno::EBADF.into();
+        }
+        cur_prc_inner.fd_table[fd].take();
+        0
+    }
+
+    pub fn kernel_dup(fd: usize) -> isize {
+        let cur_prc = current_prc().unwrap();
+        let mut cur_prc_inner = cur_prc.inner();
+        if let Some(file) = cur_prc_inner.get_file(fd) {
+            match cur_prc_inner.alloc_fd(file) {
+                Ok(fd) => fd as isize,
+                Err(errno) => errno.into(),
+            }
+        } else {
+            Errno::EBADF.into()
+        }
+    }
+
+    pub fn kernel_getrlimit(resource: usize, rlimit: *mut RLimit) -> isize {
+        let cur_prc = current_prc().unwrap();
+        let mut cur_prc_inner = cur_prc.inner();
+        let value = match cur_prc_inner.getrlimit(resource) {
+            Some(value) => value,
+            None => return Errno::EINVAL.into(),
+        };
+        match UserPtr::new(rlimit as *const RLimit).write(&mut cur_prc_inner, value) {
+            Ok(()) => 0,
+            Err(errno) => errno.into(),
+        }
+    }
+
+    pub fn kernel_setrlimit(resource: usize, rlimit: *const RLimit) -> isize {
+        let cur_prc = current_prc().unwrap();
+        let mut cur_prc_inner = cur_prc.inner();
+        let value = match UserPtr::new(rlimit).read(&mut cur_prc_inner) {
+            Ok(value) => value,
+            Err(errno) => return errno.into(),
+        };
+        cur_prc_inner.setrlimit(resource, value)
+    }
+
+    pub fn kernel_pipe(pipe: *mut usize) -> isize {
+        let cur_prc = current_prc().unwrap();
+        let mut cur_prc_inner = cur_prc.inner();
+        //check the user array first so a bad pointer leaks no fds
+        let pipe = UserPtr::new(pipe as *const [usize; 2]);
+        if let Err(errno) = pipe.write(&mut cur_prc_inner, [0; 2]) {
+            return errno.into();
+        }
+        let (read_end, write_end) = make_pipe();
+        let read_fd = match cur_prc_inner.alloc_fd(read_end) {
+            Ok(fd) => fd,
+            Err(errno) => return errno.into(),
+        };
+        let write_fd = match cur_prc_inner.alloc_fd(write_end) {
+            Ok(fd) => fd,
+            Err(errno) => {
+                cur_prc_inner.fd_table[read_fd].take();
+                return errno.into();
+            }
+        };
+        pipe.write(&mut cur_prc_inner, [read_fd, write_fd]).unwrap();
+        0
+    }
+
+    //signum 0 only checks that pid exists. The signal is acted on when the
+    //target next returns to user mode, blocked threads are woken to get there
+    pub fn kernel_kill(pid: usize, signum: usize) -> isize {
+        if signum > MAX_SIG {
+            return Errno::EINVAL.into();
+        }
+        let prc = match pid2prc(pid) {
+            Some(prc) => prc,
+            None => return Errno::ESRCH.into(),
+        };
+        if signum != 0 {
+            let mut prc_inner = prc.inner();
+            prc_inner.signals.raise(signum);
+            for thread in prc_inner.threads.iter().flatten() {
+                let interrupting = prc_inner.signals.interrupting(&thread.inner().signals);
+                if interrupting {
+                    Scheduler::wake(thread.clone());
+                }
+            }
+        }
+        0
+    }
+
+    pub fn kernel_sigaction(signum: usize, action: *const SignalAction, old_action: *mut SignalAction) -> isize {
+        if signum == 0 || signum > MAX_SIG || sig_uncatchable(signum) {
+            return Errno::EINVAL.into();
+        }
+        let cur_prc = current_prc().unwrap();
+        let mut cur_prc_inner = cur_prc.inner();
+        let old = cur_prc_inner.signals.actions[signum];
+        let action = UserPtr::new(action);
+        let new = if action.is_null() {
+            old
+        } else {
+            match action.read(&mut cur_prc_inner) {
+                Ok(new) => new,
+                Err(errno) => return errno.into(),
+            }
+        };
+        let old_action = UserPtr::new(old_action as *const SignalAction);
+        if !old_action.is_null() {
+            if let Err(errno) = old_action.write(&mut cur_prc_inner, old) {
+                return errno.into();
+            }
+        }
+        cur_prc_inner.signals.actions[signum] = new;
+        0
+    }
+
+    //the mask of the calling thread
+    pub fn kernel_sigprocmask(how: usize, set: *const usize, old_set: *mut usize) -> isize {
+        let cur_thread = current_thread().unwrap();
+        let cur_prc = cur_thread.process.upgrade().unwrap();
+        let mut cur_prc_inner = cur_prc.inner();
+        let old = cur_thread.inner().signals.mask;
+        let old_set = UserPtr::new(old_set as *const usize);
+        if !old_set.is_null() {
+            if let Err(errno) = old_set.write(&mut cur_prc_inner, old) {
+                return errno.into();
+            }
+        }
+        let set = UserPtr::new(set);
+        if set.is_null() {
+            return 0;
+        }
+        let set = match set.read(&mut cur_prc_inner) {
+            Ok(set) => set,
+            Err(errno) => return errno.into(),
+        };
+        let mask = match how {
+            SIG_BLOCK => old | set,
+            SIG_UNBLOCK => old & !set,
+            SIG_SETMASK => set,
+            _ => return Errno::EINVAL.into(),
+        };
+        cur_thread.inner().signals.mask = mask & !sig_bit(SIGKILL);
+        0
+    }
+
+    //back to where the handler interrupted, a0 included
+    pub fn kernel_sigreturn() -> isize {
+        let cur_thread = current_thread().unwrap();
+        let mut cur_thread_inner = cur_thread.inner();
+        let (trap_cxt, mask) = match cur_thread_inner.signals.backup.take() {
+            Some(backup) => backup,
+            None => return Errno::EINVAL.into(),
+        };
+        cur_thread_inner.signals.mask = mask;
+        *cur_thread_inner.get_trap_cxt() = trap_cxt;
+        trap_cxt.x[10] as isize
+    }
+
+    //a blocking syscall of the current thread should give up, see WaitQueue::wait_then
+    pub fn interrupted() -> bool {
+        let cur_thread = current_thread().unwrap();
+        let cur_prc = cur_thread.process.upgrade().unwrap();
+        let result = cur_prc.inner().signals.interrupting(&cur_thread.inner().signals);
+        result
+    }
+
+    //a signal the current process brought on itself, like SIGPIPE
+    pub fn kernel_raise_signal(signum: usize) {
+        let cur_prc = current_prc().unwrap();
+        cur_prc.inner().signals.raise(signum);
+    }
+
+    //a fault of the current process, see SignalState::force
+    pub fn kernel_fault_signal(signum: usize) {
+        let cur_thread = current_thread().unwrap();
+        let cur_prc = cur_thread.process.upgrade().unwrap();
+        cur_prc.inner().signals.force(signum, &mut cur_thread.inner().signals);
+    }
+
+    //act on pending signals right before returning to user mode. A handler
+    //is entered by rewriting the trap context, sigreturn restores it
+    pub fn handle_signals() {
+        loop {
+            let cur_thread = current_thread().unwrap();
+            let cur_prc = cur_thread.process.upgrade().unwrap();
+            let mut cur_prc_inner = cur_prc.inner();
+            let next = cur_prc_inner.signals.take_next(&cur_thread.inner().signals);
+            let (signum, delivery) = match next {
+                Some(next) => next,
+                None => return,
+            };
+            match delivery {
+                SignalDelivery::Ignore => {}
+                SignalDelivery::Terminate(exit_code) => {
+                    println!("[kernel] Process {} killed by signal {}.", cur_prc_inner.pid, signum);
+                    drop(cur_prc_inner);
+                    drop(cur_prc);
+                    drop(cur_thread);
+                    //the whole process, whichever thread noticed the signal
+                    Scheduler::exit_process(exit_code);
+                }
+                SignalDelivery::Handle(action) => {
+                    let mut cur_thread_inner = cur_thread.inner();
+                    let trap_cxt = cur_thread_inner.get_trap_cxt();
+                    let mask = cur_thread_inner.signals.mask;
+                    cur_thread_inner.signals.backup = Some((*trap_cxt, mask));
+                    cur_thread_inner.signals.mask |= action.mask | sig_bit(signum);
+                    trap_cxt.sepc = action.handler;
+                    trap_cxt.x[1] = action.restorer;
+                    trap_cxt.x[10] = signum;
+                    return;
+                }
+            }
         }
     }
 }
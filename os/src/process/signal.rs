@@ -0,0 +1,175 @@
+use crate::trap::trap_context::TrapContext;
+
+pub const SIGHUP: usize = 1;
+pub const SIGINT: usize = 2;
+pub const SIGQUIT: usize = 3;
+pub const SIGILL: usize = 4;
+pub const SIGTRAP: usize = 5;
+pub const SIGABRT: usize = 6;
+pub const SIGBUS: usize = 7;
+pub const SIGFPE: usize = 8;
+pub const SIGKILL: usize = 9;
+pub const SIGUSR1: usize = 10;
+pub const SIGSEGV: usize = 11;
+pub const SIGUSR2: usize = 12;
+pub const SIGPIPE: usize = 13;
+pub const SIGALRM: usize = 14;
+pub const SIGTERM: usize = 15;
+pub const SIGCHLD: usize = 17;
+pub const MAX_SIG: usize = 31;
+
+pub const SIG_DFL: usize = 0;
+pub const SIG_IGN: usize = 1;
+
+//how of sigprocmask
+pub const SIG_BLOCK: usize = 0;
+pub const SIG_UNBLOCK: usize = 1;
+pub const SIG_SETMASK: usize = 2;
+
+pub fn sig_bit(signum: usize) -> usize {
+    1 << signum
+}
+
+//can neither be caught, ignored nor blocked
+pub fn sig_uncatchable(signum: usize) -> bool {
+    signum == SIGKILL
+}
+
+//what SIG_DFL does, everything that is not ignored terminates
+pub fn sig_default_ignored(signum: usize) -> bool {
+    signum == SIGCHLD
+}
+
+//exit code of a process killed by signum, faults keep their old codes
+pub fn sig_exit_code(signum: usize) -> i32 {
+    match signum {
+        SIGSEGV => -2,
+        SIGILL => -3,
+        _ => -(signum as i32),
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct SignalAction {
+    //SIG_DFL, SIG_IGN or the user handler address
+    pub handler: usize,
+    //also blocked while the handler runs
+    pub mask: usize,
+    //where the handler returns to, it must call sigreturn
+    pub restorer: usize,
+}
+
+impl SignalAction {
+    pub fn default() -> Self {
+        SignalAction { handler: SIG_DFL, mask: 0, restorer: 0 }
+    }
+}
+
+//shared by the threads of a process, whichever thread does not block a
+//pending signal takes it
+pub struct SignalState {
+    pub pending: usize,
+    pub actions: [SignalAction; MAX_SIG + 1],
+}
+
+//what every thread has for itself
+#[derive(Copy, Clone)]
+pub struct ThreadSignals {
+    pub mask: usize,
+    //user context and mask to go back to in sigreturn, Some while a handler runs
+    pub backup: Option<(TrapContext, usize)>,
+}
+
+impl ThreadSignals {
+    //new threads and forked children start with the mask of their creator
+    pub fn new(mask: usize) -> Self {
+        ThreadSignals { mask, backup: None }
+    }
+}
+
+pub enum SignalDelivery {
+    Ignore,
+    Terminate(i32),
+    Handle(SignalAction),
+}
+
+impl SignalState {
+    pub fn new() -> Self {
+        SignalState {
+            pending: 0,
+            actions: [SignalAction::default(); MAX_SIG + 1],
+        }
+    }
+
+    //a forked child keeps the actions but nothing pending
+    pub fn fork(&self) -> Self {
+        SignalState {
+            pending: 0,
+            actions: self.actions,
+        }
+    }
+
+    //handlers are gone with the old image, ignored signals stay ignored
+    pub fn exec(&mut self) {
+        for action in self.actions.iter_mut() {
+            if action.handler != SIG_IGN {
+                *action = SignalAction::default();
+            }
+        }
+    }
+
+    pub fn raise(&mut self, signum: usize) {
+        self.pending |= sig_bit(signum);
+    }
+
+    //a fault the faulting thread cannot handle right now kills the process, like linux does
+    pub fn force(&mut self, signum: usize, thread: &mut ThreadSignals) {
+        let action = &mut self.actions[signum];
+        if thread.mask & sig_bit(signum) != 0 || action.handler == SIG_IGN || thread.backup.is_some() {
+            *action = SignalAction::default();
+            thread.mask &= !sig_bit(signum);
+        }
+        self.raise(signum);
+    }
+
+    //what thread taking signum would do now, None while it has to stay pending
+    fn delivery(&self, signum: usize, thread: &ThreadSignals) -> Option<SignalDelivery> {
+        if self.pending & sig_bit(signum) == 0 {
+            return None;
+        }
+        if !sig_uncatchable(signum) && thread.mask & sig_bit(signum) != 0 {
+            return None;
+        }
+        let action = self.actions[signum];
+        match action.handler {
+            _ if sig_uncatchable(signum) => Some(SignalDelivery::Terminate(sig_exit_code(signum))),
+            SIG_IGN => Some(SignalDelivery::Ignore),
+            SIG_DFL if sig_default_ignored(signum) => Some(SignalDelivery::Ignore),
+            SIG_DFL => Some(SignalDelivery::Terminate(sig_exit_code(signum))),
+            _ if thread.backup.is_some() => None,
+            _ => Some(SignalDelivery::Handle(action)),
+        }
+    }
+
+    //take the lowest pending signal thread does not block. Handlers do not
+    //nest, others wait until sigreturn unless they terminate the process
+    pub fn take_next(&mut self, thread: &ThreadSignals) -> Option<(usize, SignalDelivery)> {
+        for signum in 1..=MAX_SIG {
+            if let Some(delivery) = self.delivery(signum, thread) {
+                self.pending &= !sig_bit(signum);
+                return Some((signum, delivery));
+            }
+        }
+        None
+    }
+
+    //a pending signal that terminates or runs a handler in thread, its blocking
+    //syscalls give up for it
+    pub fn interrupting(&self, thread: &ThreadSignals) -> bool {
+        (1..=MAX_SIG).any(|signum| matches!(
+            self.delivery(signum, thread),
+            Some(SignalDelivery::Terminate(_)) | Some(SignalDelivery::Handle(_))
+        ))
+    }
+}
@@ -0,0 +1,107 @@
+use alloc::sync::{Arc, Weak};
+use core::sync::atomic::AtomicBool;
+use crate::mm::kernel_space::{kernel_stack_alloc, kernel_stack_dealloc, kernel_stack_top};
+use crate::mm::{page_num_to_addr, PAGE_SIZE, PhysPageNum, TRAP_CONTEXT, VirAddr};
+use crate::process::context::Context;
+use crate::process::process::{Process, ProcessWrapper};
+use crate::process::signal::ThreadSignals;
+use crate::sync::cell::{Mutex, MutexGuard};
+use crate::syscall::errno::Errno;
+use crate::trap::trap_context::TrapContext;
+
+//tids of one process, bounds the trap context pages below TRAP_CONTEXT
+pub const MAX_THREADS: usize = 256;
+
+//every thread has its own trap context page, the main thread's is at TRAP_CONTEXT
+pub fn trap_cxt_bottom(tid: usize) -> VirAddr {
+    TRAP_CONTEXT - tid * PAGE_SIZE
+}
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum ThreadStatus {
+    Running,
+    Ready,
+    Blocked,
+    Dead,
+}
+
+pub struct Thread {
+    pub tid: usize,
+    //id of the kernel stack, see kernel_stack_alloc
+    pub kstack: usize,
+    pub process: Weak<ProcessWrapper>,
+    //set while a hart runs the thread, cleared only once that hart has
+    //switched off its kernel stack. No other hart may resume it before
+    pub on_cpu: AtomicBool,
+    inner: Mutex<ThreadInner>,
+}
+
+pub struct ThreadInner {
+    pub context: Context,
+    pub status: ThreadStatus,
+    pub trap_context_ppn: PhysPageNum,
+    //[bottom, top) of a stack from thread_create, the main thread uses the process stack
+    pub ustack: Option<(VirAddr, VirAddr)>,
+    //Some once the thread has exited, until waittid reaps it
+    pub exit_code: Option<i32>,
+    pub signals: ThreadSignals,
+}
+
+impl Thread {
+    //a new thread of prc with its own trap context page and kernel stack, plus
+    //a user stack unless it is the main thread. It is registered in prc.threads
+    pub fn new(prc: &Arc<ProcessWrapper>, prc_inner: &mut Process, ustack: bool, sig_mask: usize) -> Result<Arc<Thread>, Errno> {
+        let tid = prc_inner.tid_allocator.alloc().ok_or(Errno::EAGAIN)?;
+        let ustack = if ustack {
+            match prc_inner.alloc_thread_stack() {
+                Some(range) => Some(range),
+                None => {
+                    prc_inner.tid_allocator.dealloc(tid);
+                    return Err(Errno::ENOMEM);
+                }
+            }
+        } else {
+            None
+        };
+        let trap_context_ppn = prc_inner.map_trap_cxt(tid);
+        let kstack = kernel_stack_alloc();
+        let thread = Arc::new(Thread {
+            tid,
+            kstack,
+            process: Arc::downgrade(prc),
+            on_cpu: AtomicBool::new(false),
+            inner: Mutex::new(ThreadInner {
+                context: Context::goto_trap_return(kernel_stack_top(kstack)),
+                status: ThreadStatus::Ready,
+                trap_context_ppn,
+                ustack,
+                exit_code: None,
+                signals: ThreadSignals::new(sig_mask),
+            }),
+        });
+        if prc_inner.threads.len() <= tid {
+            prc_inner.threads.resize(tid + 1, None);
+        }
+        prc_inner.threads[tid] = Some(thread.clone());
+        Ok(thread)
+    }
+
+    pub fn inner(&self) -> MutexGuard<ThreadInner> {
+        self.inner.lock()
+    }
+}
+
+impl ThreadInner {
+    pub fn get_trap_cxt(&self) -> &'static mut TrapContext {
+        unsafe {
+            (page_num_to_addr(self.trap_context_ppn) as *mut TrapContext).as_mut().unwrap()
+        }
+    }
+}
+
+//the last reference is dropped only after the thread switched away for good
+impl Drop for Thread {
+    fn drop(&mut self) {
+        kernel_stack_dealloc(self.kstack);
+    }
+}
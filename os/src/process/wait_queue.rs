@@ -0,0 +1,66 @@
+use alloc::collections::VecDeque;
+use alloc::sync::Arc;
+
+use crate::process::processor::current_thread;
+use crate::process::scheduler::Scheduler;
+use crate::process::thread::{Thread, ThreadStatus};
+use crate::sync::cell::Mutex;
+
+//threads blocked on one event, they stay off the available queue until woken
+pub struct WaitQueue {
+    queue: Mutex<VecDeque<Arc<Thread>>>,
+}
+
+impl WaitQueue {
+    pub fn new() -> Self {
+        WaitQueue {
+            queue: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    //block the current thread until someone wakes this queue, callers recheck
+    //their condition after returning. release runs once the thread is queued, so
+    //a waker on another hart that needs what it gives up cannot miss the thread.
+    //False if a signal interrupted the wait, the syscall should fail with EINTR
+    pub fn wait_then(&self, release: impl FnOnce()) -> bool {
+        let cur_thread = current_thread().unwrap();
+        if Scheduler::set_alive_status(&cur_thread, ThreadStatus::Blocked) {
+            self.queue.lock().push_back(cur_thread.clone());
+        }
+        release();
+        //kill only wakes threads that are already Blocked
+        if Scheduler::interrupted() {
+            Scheduler::wake(cur_thread.clone());
+        }
+        Scheduler::switch_away(cur_thread);
+        //still queued if the signal woke it
+        let cur_thread = current_thread().unwrap();
+        self.queue.lock().retain(|thread| !Arc::ptr_eq(thread, &cur_thread));
+        !Scheduler::interrupted()
+    }
+
+    //false if no thread was made runnable, entries a signal already woke are dropped
+    pub fn wake_one(&self) -> bool {
+        loop {
+            let thread = self.queue.lock().pop_front();
+            match thread {
+                Some(thread) if Scheduler::wake(thread) => return true,
+                Some(_) => {}
+                None => return false,
+            }
+        }
+    }
+
+    pub fn wake_all(&self) {
+        while self.wake_one() {}
+    }
+
+    //threads killed while they waited never come back to take themselves out
+    pub fn remove_dead(&self) {
+        self.queue.lock().retain(|thread| thread.inner().status != ThreadStatus::Dead);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queue.lock().is_empty()
+    }
+}
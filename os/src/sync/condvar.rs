@@ -0,0 +1,35 @@
+use crate::process::wait_queue::WaitQueue;
+use crate::sync::mutex::UserMutex;
+use crate::syscall::errno::Errno;
+
+pub struct Condvar {
+    waiters: WaitQueue,
+}
+
+impl Condvar {
+    pub fn new() -> Self {
+        Condvar {
+            waiters: WaitQueue::new(),
+        }
+    }
+
+    pub fn signal(&self) {
+        self.waiters.wake_one();
+    }
+
+    //the mutex is unlocked only once the thread is queued, so a signal
+    //sent after the unlock cannot be missed. Callers recheck on return.
+    //EPERM unless tid holds the mutex, after EINTR it is not held any more
+    pub fn wait(&self, mutex: &UserMutex, tid: usize) -> Result<(), Errno> {
+        if !mutex.held_by(tid) {
+            return Err(Errno::EPERM);
+        }
+        //only the holder could unlock it in between, which is tid
+        let woken = self.waiters.wait_then(|| assert!(mutex.unlock(tid)));
+        if woken && mutex.lock(tid) {
+            Ok(())
+        } else {
+            Err(Errno::EINTR)
+        }
+    }
+}
@@ -0,0 +1,65 @@
+use alloc::collections::BTreeMap;
+use alloc::sync::Arc;
+use core::sync::atomic::{AtomicU32, Ordering};
+use lazy_static::lazy_static;
+use crate::mm::PhyAddr;
+use crate::process::wait_queue::WaitQueue;
+use crate::sync::cell::Mutex;
+use crate::syscall::errno::Errno;
+
+pub const FUTEX_WAIT: usize = 0;
+pub const FUTEX_WAKE: usize = 1;
+//every futex is private to its process here, the flag is accepted and ignored
+pub const FUTEX_PRIVATE_FLAG: usize = 128;
+
+lazy_static! {
+    //threads waiting on a futex word, keyed by its physical address.
+    //Queues go away once nobody waits on them
+    static ref FUTEX_QUEUES: Mutex<BTreeMap<PhyAddr, Arc<WaitQueue>>> = Mutex::new(BTreeMap::new());
+}
+
+//blocks while the futex word at key holds val, EAGAIN if it did not. Wakers change
+//the word before they take FUTEX_QUEUES, so checking under it misses none of them
+pub fn futex_wait(key: PhyAddr, val: u32) -> Result<(), Errno> {
+    let mut queues = FUTEX_QUEUES.lock();
+    let word = unsafe { (*(key as *const AtomicU32)).load(Ordering::SeqCst) };
+    if word != val {
+        return Err(Errno::EAGAIN);
+    }
+    let queue = queues.entry(key).or_insert_with(|| Arc::new(WaitQueue::new())).clone();
+    if queue.wait_then(|| drop(queues)) {
+        return Ok(());
+    }
+    //no waker took it out, it may have been the last one
+    let mut queues = FUTEX_QUEUES.lock();
+    if queue.is_empty() && queues.get(&key).map_or(false, |q| Arc::ptr_eq(q, &queue)) {
+        queues.remove(&key);
+    }
+    Err(Errno::EINTR)
+}
+
+//forget waiters killed by kill_other_threads, the queues of words nobody
+//wakes any more would keep them forever
+pub fn futex_remove_dead() {
+    FUTEX_QUEUES.lock().retain(|_, queue| {
+        queue.remove_dead();
+        !queue.is_empty()
+    });
+}
+
+//returns how many waiters were woken, at most max
+pub fn futex_wake(key: PhyAddr, max: usize) -> usize {
+    let mut queues = FUTEX_QUEUES.lock();
+    let queue = match queues.get(&key) {
+        Some(queue) => queue.clone(),
+        None => return 0,
+    };
+    let mut woken = 0;
+    while woken < max && queue.wake_one() {
+        woken += 1;
+    }
+    if queue.is_empty() {
+        queues.remove(&key);
+    }
+    woken
+}
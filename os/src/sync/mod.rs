@@ -1,2 +1,6 @@
 pub mod cell;
-pub mod spinlock;
\ No newline at end of file
+pub mod condvar;
+pub mod futex;
+pub mod mutex;
+pub mod semaphore;
+pub mod spinlock;
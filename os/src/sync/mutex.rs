@@ -0,0 +1,48 @@
+use crate::process::wait_queue::WaitQueue;
+use crate::sync::cell::Mutex;
+
+//mutex for user threads, contenders block instead of spinning
+pub struct UserMutex {
+    //tid of the holder
+    owner: Mutex<Option<usize>>,
+    waiters: WaitQueue,
+}
+
+impl UserMutex {
+    pub fn new() -> Self {
+        UserMutex {
+            owner: Mutex::new(None),
+            waiters: WaitQueue::new(),
+        }
+    }
+
+    //false if a signal interrupted the wait
+    pub fn lock(&self, tid: usize) -> bool {
+        loop {
+            let mut owner = self.owner.lock();
+            if owner.is_none() {
+                *owner = Some(tid);
+                return true;
+            }
+            if !self.waiters.wait_then(|| drop(owner)) {
+                return false;
+            }
+        }
+    }
+
+    pub fn held_by(&self, tid: usize) -> bool {
+        *self.owner.lock() == Some(tid)
+    }
+
+    //false unless tid holds it
+    pub fn unlock(&self, tid: usize) -> bool {
+        let mut owner = self.owner.lock();
+        if *owner != Some(tid) {
+            return false;
+        }
+        *owner = None;
+        drop(owner);
+        self.waiters.wake_one();
+        true
+    }
+}
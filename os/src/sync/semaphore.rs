@@ -0,0 +1,35 @@
+use crate::process::wait_queue::WaitQueue;
+use crate::sync::cell::Mutex;
+
+pub struct Semaphore {
+    count: Mutex<usize>,
+    waiters: WaitQueue,
+}
+
+impl Semaphore {
+    pub fn new(count: usize) -> Self {
+        Semaphore {
+            count: Mutex::new(count),
+            waiters: WaitQueue::new(),
+        }
+    }
+
+    pub fn up(&self) {
+        *self.count.lock() += 1;
+        self.waiters.wake_one();
+    }
+
+    //blocks while the count is 0, false if a signal interrupted the wait
+    pub fn down(&self) -> bool {
+        loop {
+            let mut count = self.count.lock();
+            if *count > 0 {
+                *count -= 1;
+                return true;
+            }
+            if !self.waiters.wait_then(|| drop(count)) {
+                return false;
+            }
+        }
+    }
+}
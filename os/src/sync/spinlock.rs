@@ -1,20 +1,21 @@
+use core::hint::spin_loop;
 use core::sync::atomic::{AtomicBool, Ordering};
 use crate::println;
-use crate::process::scheduler::{SCHEDULER, Scheduler};
 
+//the holder may be running on another hart, so wait it out instead of switching
 pub struct Spinlock {
     if_lock: AtomicBool,
 }
 
 impl Spinlock {
     pub fn lock(&self) {
-        while self.if_lock.compare_exchange(false, true, Ordering::Acquire, Ordering::Acquire).is_err() {
-            Scheduler::kernel_yield();
+        while self.if_lock.compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed).is_err() {
+            spin_loop();
         }
     }
 
     pub fn unlock(&self) {
-        if self.if_lock.compare_exchange(true, false, Ordering::Relaxed, Ordering::Relaxed).is_ok() {
+        if self.if_lock.compare_exchange(true, false, Ordering::Release, Ordering::Relaxed).is_ok() {
             return;
         } else { println! {"Alarm: attempt to unlock a free lock"}; }
     }
@@ -25,4 +26,4 @@ impl Spinlock {
             if_lock:AtomicBool::new(false)
         }
     }
-}
\ No newline at end of file
+}
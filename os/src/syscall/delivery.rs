@@ -1,52 +1,45 @@
-use crate::io::uart::uart_getchar;
-use crate::mm::pagetable::PageTable;
+use crate::fs::Stat;
 use crate::{print, println};
+use crate::process::process::RLimit;
+use crate::process::signal::{SignalAction, SIGPIPE};
 use crate::process::scheduler::{SCHEDULER, Scheduler};
-use crate::utility::timer::get_time;
-
-const STDIN: usize = 0;
-const STDOUT: usize = 1;
+use crate::syscall::errno::Errno;
+use crate::utility::timer::{get_time, get_time_ms, ms_to_ticks, TimeSpec};
 
 pub fn sys_write(fd: usize, buf: *const u8, len: usize) -> isize {
-    match fd {
-        STDOUT => {
-            let buffers = PageTable::from_token(Scheduler::get_cur_token()).translated_byte_buffer(buf, len);
-            for buffer in buffers {
-                print!("{}", core::str::from_utf8(buffer).unwrap());
-            }
-            len as isize
-        }
-        _ => {
-            panic!("Unsupported fd in sys_write!");
+    let file = match Scheduler::get_cur_file(fd) {
+        Some(file) if file.writable() => file,
+        _ => return Errno::EBADF.into(),
+    };
+    let buffer = match Scheduler::user_buffer(buf, len, false) {
+        Ok(buffer) => buffer,
+        Err(errno) => return errno.into(),
+    };
+    match file.write(buffer) {
+        //a blocking write gave up for a signal before moving anything
+        Ok(0) if len > 0 && Scheduler::interrupted() => Errno::EINTR.into(),
+        Ok(written) => written as isize,
+        Err(Errno::EPIPE) => {
+            Scheduler::kernel_raise_signal(SIGPIPE);
+            Errno::EPIPE.into()
         }
+        Err(errno) => errno.into(),
     }
 }
 
 pub fn sys_read(fd: usize, buf: *const u8, len: usize) -> isize {
-    match fd {
-        STDIN => {
-            let mut c: usize;
-            loop {
-                unsafe {
-                    c = uart_getchar() as usize;
-                }
-                if c == 0 {
-                    Scheduler::kernel_yield();
-                    continue;
-                } else {
-                    break;
-                }
-            }
-            let ch = c as u8;
-            let mut buffers = PageTable::from_token(Scheduler::get_cur_token()).translated_byte_buffer( buf, len);
-            unsafe {
-                buffers[0].as_mut_ptr().write_volatile(ch);
-            }
-            1
-        }
-        _ => {
-            panic!("Unsupported fd in sys_read!");
-        }
+    let file = match Scheduler::get_cur_file(fd) {
+        Some(file) if file.readable() => file,
+        _ => return Errno::EBADF.into(),
+    };
+    let buffer = match Scheduler::user_buffer(buf, len, true) {
+        Ok(buffer) => buffer,
+        Err(errno) => return errno.into(),
+    };
+    match file.read(buffer) {
+        Ok(0) if len > 0 && Scheduler::interrupted() => Errno::EINTR.into(),
+        Ok(read) => read as isize,
+        Err(errno) => errno.into(),
     }
 }
 
@@ -72,15 +65,141 @@ pub fn sys_get_time() -> isize {
     unsafe{get_time() as isize}
 }
 
-pub fn sys_exec(path: *const u8) -> isize {
-    Scheduler::kernel_exec(path)
+pub fn sys_get_time_ms() -> isize {
+    get_time_ms() as isize
+}
+
+pub fn sys_sleep(ms: usize) -> isize {
+    Scheduler::kernel_sleep(ms_to_ticks(ms))
+}
+
+pub fn sys_nanosleep(req: *const TimeSpec) -> isize {
+    Scheduler::kernel_nanosleep(req)
+}
+
+pub fn sys_brk(new_brk: usize) -> isize {
+    Scheduler::kernel_brk(new_brk)
+}
+
+pub fn sys_mmap(start: usize, len: usize, prot: usize) -> isize {
+    Scheduler::kernel_mmap(start, len, prot)
+}
+
+pub fn sys_munmap(start: usize, len: usize) -> isize {
+    Scheduler::kernel_munmap(start, len)
+}
+
+pub fn sys_mprotect(start: usize, len: usize, prot: usize) -> isize {
+    Scheduler::kernel_mprotect(start, len, prot)
+}
+
+pub fn sys_getrlimit(resource: usize, rlimit: *mut RLimit) -> isize {
+    Scheduler::kernel_getrlimit(resource, rlimit)
+}
+
+pub fn sys_setrlimit(resource: usize, rlimit: *const RLimit) -> isize {
+    Scheduler::kernel_setrlimit(resource, rlimit)
+}
+
+pub fn sys_kill(pid: usize, signum: usize) -> isize {
+    Scheduler::kernel_kill(pid, signum)
+}
+
+pub fn sys_sigaction(signum: usize, action: *const SignalAction, old_action: *mut SignalAction) -> isize {
+    Scheduler::kernel_sigaction(signum, action, old_action)
+}
+
+pub fn sys_sigprocmask(how: usize, set: *const usize, old_set: *mut usize) -> isize {
+    Scheduler::kernel_sigprocmask(how, set, old_set)
+}
+
+pub fn sys_sigreturn() -> isize {
+    Scheduler::kernel_sigreturn()
+}
+
+pub fn sys_exec(path: *const u8, argv: *const usize, envp: *const usize) -> isize {
+    Scheduler::kernel_exec(path, argv, envp)
 }
 
 pub fn sys_waitpid(pid: isize, exit_code_ptr: *mut i32) -> isize {
     Scheduler::kernel_waitpid(pid, exit_code_ptr)
 }
 
-pub fn sys_open() -> isize {
-    0
-    //todo
-}
\ No newline at end of file
+pub fn sys_thread_create(entry: usize, arg: usize) -> isize {
+    Scheduler::kernel_thread_create(entry, arg)
+}
+
+pub fn sys_gettid() -> isize {
+    Scheduler::kernel_gettid() as isize
+}
+
+pub fn sys_waittid(tid: usize) -> isize {
+    Scheduler::kernel_waittid(tid)
+}
+
+pub fn sys_futex(addr: usize, op: usize, val: usize) -> isize {
+    Scheduler::kernel_futex(addr, op, val)
+}
+
+pub fn sys_mutex_create() -> isize {
+    Scheduler::kernel_mutex_create()
+}
+
+pub fn sys_mutex_lock(id: usize) -> isize {
+    Scheduler::kernel_mutex_lock(id)
+}
+
+pub fn sys_mutex_unlock(id: usize) -> isize {
+    Scheduler::kernel_mutex_unlock(id)
+}
+
+pub fn sys_semaphore_create(count: usize) -> isize {
+    Scheduler::kernel_semaphore_create(count)
+}
+
+pub fn sys_semaphore_up(id: usize) -> isize {
+    Scheduler::kernel_semaphore_up(id)
+}
+
+pub fn sys_semaphore_down(id: usize) -> isize {
+    Scheduler::kernel_semaphore_down(id)
+}
+
+pub fn sys_condvar_create() -> isize {
+    Scheduler::kernel_condvar_create()
+}
+
+pub fn sys_condvar_signal(id: usize) -> isize {
+    Scheduler::kernel_condvar_signal(id)
+}
+
+pub fn sys_condvar_wait(id: usize, mutex_id: usize) -> isize {
+    Scheduler::kernel_condvar_wait(id, mutex_id)
+}
+
+pub fn sys_open(path: *const u8, flags: u32) -> isize {
+    Scheduler::kernel_open(path, flags)
+}
+
+pub fn sys_close(fd: usize) -> isize {
+    Scheduler::kernel_close(fd)
+}
+
+pub fn sys_dup(fd: usize) -> isize {
+    Scheduler::kernel_dup(fd)
+}
+
+pub fn sys_pipe(pipe: *mut usize) -> isize {
+    Scheduler::kernel_pipe(pipe)
+}
+
+pub fn sys_fstat(fd: usize, stat_ptr: *mut Stat) -> isize {
+    let file = match Scheduler::get_cur_file(fd) {
+        Some(file) => file,
+        None => return Errno::EBADF.into(),
+    };
+    match Scheduler::write_user(stat_ptr, file.stat()) {
+        Ok(()) => 0,
+        Err(errno) => errno.into(),
+    }
+}
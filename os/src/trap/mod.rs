@@ -1,13 +1,22 @@
 pub mod trap_context;
 
 use core::arch::{asm, global_asm};
-use riscv::register::{mie, mtvec::TrapMode, satp, scause::{self, Exception, Interrupt, Trap}, sepc, sie, stval, stvec};
-use crate::mm::{TRAMPOLINE, TRAP_CONTEXT};
-use crate::println;
-use crate::process::scheduler::{Scheduler, SCHEDULER};
+use riscv::register::{mie, mtvec::TrapMode, satp, scause::{self, Exception, Interrupt, Trap}, sepc, sie, sip, stval, stvec};
+use crate::mm::TRAMPOLINE;
+use crate::mm::kernel_space::kernel_stack_guard_owner;
+use crate::mm::tlb::{enter_user, leave_user};
+use crate::{println, CPUS};
+use crate::io::external_interrupt;
+use crate::process::scheduler::Scheduler;
+use crate::process::signal::{SIGILL, SIGSEGV};
+use crate::process::thread::trap_cxt_bottom;
 use crate::syscall::syscall;
+use crate::utility::hart_id;
+use crate::utility::timer::{check_timers, set_next_trigger};
 
-global_asm!(include_str!("trap.S"));
+const KERNEL_TRAP_STACK_SIZE: usize = 4096 * 4;
+
+global_asm!(include_str!("trap.S"), stack_size = const KERNEL_TRAP_STACK_SIZE, cpus = const CPUS);
 
 pub fn init() {
     set_kernel_trap_entry();
@@ -19,8 +28,11 @@ pub fn init() {
 }
 
 fn set_kernel_trap_entry() {
+    extern "C" {
+        fn __kernel_trap();
+    }
     unsafe {
-        stvec::write(trap_from_kernel as usize, TrapMode::Direct);
+        stvec::write(__kernel_trap as usize, TrapMode::Direct);
     }
 }
 
@@ -33,7 +45,9 @@ fn set_user_trap_entry() {
 
 #[no_mangle]
 pub fn trap_handler() -> ! {
+    leave_user();
     set_kernel_trap_entry();
+    Scheduler::leave_if_dead();
     let scause = scause::read();
     let stval = stval::read();
     //Different action corresponding to scause
@@ -45,6 +59,18 @@ pub fn trap_handler() -> ! {
             cxt = Scheduler::get_cur_trap_cxt();
             cxt.x[10] = result as usize;
         }
+        Trap::Exception(Exception::StorePageFault) if Scheduler::kernel_page_fault(stval, true) => {}
+        Trap::Exception(Exception::LoadPageFault)
+        | Trap::Exception(Exception::InstructionPageFault) if Scheduler::kernel_page_fault(stval, false) => {}
+        Trap::Exception(Exception::StorePageFault)
+        | Trap::Exception(Exception::LoadPageFault) if Scheduler::in_stack_guard(stval) => {
+            println!(
+                "[kernel] Stack overflow in application, bad addr = {:#x}, bad instruction = {:#x}.",
+                stval,
+                Scheduler::get_cur_trap_cxt().sepc,
+            );
+            Scheduler::kernel_fault_signal(SIGSEGV);
+        }
         Trap::Exception(Exception::StoreFault)
         | Trap::Exception(Exception::StorePageFault)
         | Trap::Exception(Exception::InstructionFault)
@@ -52,21 +78,29 @@ pub fn trap_handler() -> ! {
         | Trap::Exception(Exception::LoadFault)
         | Trap::Exception(Exception::LoadPageFault) => {
             println!(
-                "[kernel] {:?} in application, bad addr = {:#x}, bad instruction = {:#x}, kernel killed it.",
+                "[kernel] {:?} in application, bad addr = {:#x}, bad instruction = {:#x}.",
                 scause.cause(),
                 stval,
                 Scheduler::get_cur_trap_cxt().sepc,
             );
-            Scheduler::kernel_exit(-2);
+            Scheduler::kernel_fault_signal(SIGSEGV);
         }
         Trap::Exception(Exception::IllegalInstruction) => {
-            println!("[kernel] IllegalInstruction in application, kernel killed it.");
-            // illegal instruction exit code
-            Scheduler::kernel_exit(-3);
+            println!("[kernel] IllegalInstruction in application, bad instruction = {:#x}.", Scheduler::get_cur_trap_cxt().sepc);
+            Scheduler::kernel_fault_signal(SIGILL);
         }
         Trap::Interrupt(Interrupt::SupervisorTimer) => {
+            set_next_trigger();
+            check_timers();
             Scheduler::kernel_yield();
         }
+        Trap::Interrupt(Interrupt::SupervisorExternal) => {
+            external_interrupt();
+        }
+        //a tlb shootdown, getting here was all it asked for
+        Trap::Interrupt(Interrupt::SupervisorSoft) => unsafe {
+            sip::clear_ssoft();
+        },
         _ => {
             panic!(
                 "Unsupported trap {:?}, stval = {:#x}!",
@@ -75,14 +109,20 @@ pub fn trap_handler() -> ! {
             );
         }
     }
+    Scheduler::handle_signals();
     trap_return();
 }
 
 #[no_mangle]
 pub fn trap_return() -> ! {
+    //a thread killed while in the kernel must not go back to freed memory
+    Scheduler::leave_if_dead();
     set_user_trap_entry();
-    let trap_cx_ptr = TRAP_CONTEXT;
+    //user code may change tp, the next trap takes the hartid from here
+    Scheduler::get_cur_trap_cxt().hartid = hart_id();
+    let trap_cx_ptr = trap_cxt_bottom(Scheduler::get_cur_tid());
     let user_satp = Scheduler::get_cur_token();
+    enter_user(user_satp);
     extern "C" {
         fn __alltraps();
         fn __restore();
@@ -102,6 +142,13 @@ pub fn trap_return() -> ! {
 
 #[no_mangle]
 pub fn trap_from_kernel() -> ! {
+    let scause = scause::read();
+    let stval = stval::read();
+    if let Trap::Exception(Exception::StorePageFault) | Trap::Exception(Exception::LoadPageFault) = scause.cause() {
+        if let Some(id) = kernel_stack_guard_owner(stval) {
+            panic!("Kernel stack {} overflow, bad addr = {:#x}, sepc = {:#x}.", id, stval, sepc::read());
+        }
+    }
     println!( "Kernel trap in scause {}, stval {:#x}, sepc {:#x}, and satp {:#x}.",
               scause::read().bits(),
               stval::read(),
@@ -1,6 +1,7 @@
 use riscv::register::sstatus::{self, Sstatus, SPP};
 
 #[repr(C)]
+#[derive(Copy, Clone)]
 pub struct TrapContext {
     pub x: [usize; 32],
     pub sstatus: Sstatus,
@@ -8,6 +9,8 @@ pub struct TrapContext {
     pub kernel_satp: usize,
     pub kernel_sp: usize,
     pub trap_handler: usize,
+    //the hart the thread last returned to user mode on, __alltraps loads it into tp
+    pub hartid: usize,
 }
 
 impl TrapContext{
@@ -28,6 +31,7 @@ impl TrapContext{
             kernel_satp,
             kernel_sp,
             trap_handler,
+            hartid: 0,
         };
         cx.x[2]=sp;
         cx
@@ -6,6 +6,34 @@ pub mod panic;
 pub mod recycle_counter;
 pub mod timer;
 
+//number set in an environment variable at build time, default if unset
+pub const fn parse_or(value: Option<&str>, default: usize) -> usize {
+    match value {
+        None => default,
+        Some(value) => {
+            let bytes = value.as_bytes();
+            let mut result = 0;
+            let mut i = 0;
+            while i < bytes.len() {
+                assert!(bytes[i].is_ascii_digit(), "build settings must be numbers");
+                result = result * 10 + (bytes[i] - b'0') as usize;
+                i += 1;
+            }
+            result
+        }
+    }
+}
+
+//tp holds the hartid in the kernel, set in rust_start and on every trap from user mode
+pub fn hart_id() -> usize {
+    let hartid: usize;
+    unsafe {
+        asm!("mv {hartid}, tp",
+        hartid = out(reg)hartid);
+    }
+    hartid
+}
+
 // lazy_static!(
 //     pub static ref HARTID: Mutex<usize> =Mutex::new(get_hartid());
 // );
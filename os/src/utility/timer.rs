@@ -1,11 +1,40 @@
+use alloc::sync::Arc;
+use alloc::vec::Vec;
 use core::arch::{asm, global_asm};
+use lazy_static::lazy_static;
 use riscv::register::{mtvec, sie, mscratch, mie, mstatus};
+use crate::CPUS;
 use crate::println;
+use crate::process::scheduler::Scheduler;
+use crate::process::thread::Thread;
+use crate::sync::cell::Mutex;
+use crate::utility::{hart_id, parse_or};
 
 pub const CLINT: usize = 0x2000000;
 pub static CLINT_MTIMECMP: usize = CLINT + 0x4000;
 pub const CLINT_MTIME: usize = CLINT + 0xbff8;
-pub const INTERVAL: usize = 1000000;
+pub const CLINT_MSIP: usize = CLINT;
+//mtime frequency of qemu virt
+pub const CLOCK_FREQ: usize = 10000000;
+//length of a time slice, set with TIME_SLICE_MS at build time
+pub const TIME_SLICE_MS: usize = parse_or(option_env!("TIME_SLICE_MS"), 10);
+pub const INTERVAL: usize = CLOCK_FREQ / 1000 * TIME_SLICE_MS;
+pub const MSEC_PER_SEC: usize = 1000;
+pub const NSEC_PER_SEC: usize = 1000000000;
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct TimeSpec {
+    pub sec: usize,
+    pub nsec: usize,
+}
+
+impl TimeSpec {
+    //the values come from user mode, a time too long to count is forever
+    pub fn to_ticks(&self) -> usize {
+        self.sec.checked_mul(CLOCK_FREQ).unwrap_or(usize::MAX).saturating_add(self.nsec / (NSEC_PER_SEC / CLOCK_FREQ))
+    }
+}
 
 global_asm!(include_str!( "timer.S"));
 
@@ -13,25 +42,39 @@ extern "C" {
     fn timervec();
 }
 
+//scratch[0..3] saves t0~t2, scratch[3] is the address of mtimecmp, scratch[4] the interval,
+//scratch[5] the address of msip. One per hart, as are mtimecmp and msip
 #[link_section = ".bss.stack"]
-pub static mut SCRATCH: [usize; 5] = [0; 5];
+pub static mut SCRATCH: [[usize; 6]; CPUS] = [[0; 6]; CPUS];
 
-pub unsafe fn init_timer() {
-    let mut hartid: usize = 0;
+fn mtimecmp(hartid: usize) -> usize {
+    CLINT_MTIMECMP + 8 * hartid
+}
+
+fn msip(hartid: usize) -> usize {
+    CLINT_MSIP + 4 * hartid
+}
+
+//raises a supervisor software interrupt on the hart, by way of timervec
+pub fn send_ipi(hartid: usize) {
     unsafe {
-        asm!("mv {hartid}, tp",
-        hartid = out(reg)hartid);
+        (msip(hartid) as *mut u32).write_volatile(1);
     }
-    assert_eq!(hartid, 0);
+}
+
+pub unsafe fn init_timer() {
+    let hartid = hart_id();
     reset_timer();
     let timervec_ptr = timervec as *mut usize;
-    let scratch = &mut SCRATCH;
-    scratch[3] = CLINT_MTIMECMP;
+    let scratch = &mut SCRATCH[hartid];
+    scratch[3] = mtimecmp(hartid);
     scratch[4] = INTERVAL;
+    scratch[5] = msip(hartid);
     mscratch::write(scratch.as_ptr() as usize);
     mtvec::write(timervec_ptr as usize, mtvec::TrapMode::Direct);
     mstatus::set_mie();
     mie::set_mtimer();
+    mie::set_msoft();
 }
 
 pub unsafe fn get_time() -> usize {
@@ -40,5 +83,55 @@ pub unsafe fn get_time() -> usize {
 
 
 pub unsafe fn reset_timer() {
-    *(CLINT_MTIMECMP as *mut usize) = get_time() + INTERVAL;
-}
\ No newline at end of file
+    *(mtimecmp(hart_id()) as *mut usize) = get_time() + INTERVAL;
+}
+
+//called from supervisor mode, asks timervec to arm the next time slice
+pub fn set_next_trigger() {
+    unsafe {
+        asm!("ecall", in("a0") get_time() + INTERVAL);
+    }
+}
+
+pub fn get_time_ms() -> usize {
+    unsafe { get_time() / (CLOCK_FREQ / MSEC_PER_SEC) }
+}
+
+pub fn ms_to_ticks(ms: usize) -> usize {
+    ms.checked_mul(CLOCK_FREQ / MSEC_PER_SEC).unwrap_or(usize::MAX)
+}
+
+struct TimerEntry {
+    deadline: usize,
+    thread: Arc<Thread>,
+}
+
+lazy_static! {
+    //sleeping threads, sorted by deadline
+    static ref TIMERS: Mutex<Vec<TimerEntry>> = Mutex::new(Vec::new());
+}
+
+//the thread must block right after this, it is woken once mtime passes deadline
+pub fn add_timer(deadline: usize, thread: Arc<Thread>) {
+    let mut timers = TIMERS.lock();
+    let idx = timers.iter().position(|t| t.deadline > deadline).unwrap_or(timers.len());
+    timers.insert(idx, TimerEntry { deadline, thread });
+}
+
+//a sleep that ended early, its entry would wake the thread for nothing later
+pub fn remove_timer(thread: &Arc<Thread>) {
+    TIMERS.lock().retain(|t| !Arc::ptr_eq(&t.thread, thread));
+}
+
+pub fn check_timers() {
+    let now = unsafe { get_time() };
+    let mut expired = Vec::new();
+    {
+        let mut timers = TIMERS.lock();
+        let n = timers.iter().take_while(|t| t.deadline <= now).count();
+        expired.extend(timers.drain(..n));
+    }
+    for timer in expired {
+        Scheduler::wake(timer.thread);
+    }
+}
@@ -0,0 +1,21 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+// run by usertests as `argv_test hello world`
+#[no_mangle]
+pub fn main(argc: usize, argv: &[&str]) -> i32 {
+    println!("argc = {}", argc);
+    for (i, arg) in argv.iter().enumerate() {
+        println!("argv[{}] = {}", i, arg);
+    }
+    assert_eq!(argc, 3);
+    assert_eq!(argv.len(), argc);
+    assert_eq!(argv[0], "argv_test");
+    assert_eq!(argv[1], "hello");
+    assert_eq!(argv[2], "world");
+    println!("argv_test passed!");
+    0
+}
@@ -0,0 +1,57 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{brk, fork, mprotect, munmap, sbrk, wait, ENOMEM, PROT_READ};
+
+const PAGE_SIZE: isize = 4096;
+
+#[no_mangle]
+pub fn main() -> i32 {
+    // the allocator took what is below, the rest of the heap is ours
+    let base = sbrk(0);
+    assert!(base > 0);
+    assert_eq!(sbrk(3 * PAGE_SIZE), base);
+    assert_eq!(sbrk(0), base + 3 * PAGE_SIZE);
+    let heap = unsafe { core::slice::from_raw_parts_mut(base as *mut u8, 3 * PAGE_SIZE as usize) };
+    for (i, byte) in heap.iter_mut().enumerate() {
+        *byte = i as u8;
+    }
+    // the heap is inherited by fork
+    let pid = fork();
+    if pid == 0 {
+        assert_eq!(heap[PAGE_SIZE as usize + 1], 1);
+        heap[0] = 0xff;
+        return 0;
+    }
+    let mut exit_code: i32 = 0;
+    assert_eq!(pid, wait(&mut exit_code));
+    assert_eq!(exit_code, 0);
+    assert_eq!(heap[0], 0);
+    // shrink and grow again, the given back page comes back zeroed
+    assert_eq!(sbrk(-2 * PAGE_SIZE), base + 3 * PAGE_SIZE);
+    assert_eq!(heap[5], 5);
+    assert_eq!(sbrk(PAGE_SIZE), base + PAGE_SIZE);
+    let heap = unsafe { core::slice::from_raw_parts_mut(base as *mut u8, 2 * PAGE_SIZE as usize) };
+    assert_eq!(heap[PAGE_SIZE as usize + 1], 0);
+    // the break cannot go below the start of the heap
+    assert_eq!(brk(PAGE_SIZE as usize), ENOMEM);
+    assert_eq!(sbrk(0), base + 2 * PAGE_SIZE);
+    // mprotect and munmap split the heap, brk still finds its top
+    assert_eq!(sbrk(2 * PAGE_SIZE), base + 2 * PAGE_SIZE);
+    assert_eq!(mprotect(base as usize, PAGE_SIZE as usize, PROT_READ), 0);
+    assert_eq!(munmap((base + PAGE_SIZE) as usize, PAGE_SIZE as usize), 0);
+    assert_eq!(sbrk(PAGE_SIZE), base + 4 * PAGE_SIZE);
+    let top = unsafe { core::slice::from_raw_parts_mut((base + 4 * PAGE_SIZE) as *mut u8, PAGE_SIZE as usize) };
+    top[PAGE_SIZE as usize - 1] = 7;
+    assert_eq!(top[PAGE_SIZE as usize - 1], 7);
+    assert_eq!(sbrk(-3 * PAGE_SIZE), base + 5 * PAGE_SIZE);
+    assert_eq!(sbrk(PAGE_SIZE), base + 2 * PAGE_SIZE);
+    let heap = unsafe { core::slice::from_raw_parts_mut(base as *mut u8, 3 * PAGE_SIZE as usize) };
+    assert_eq!(heap[2 * PAGE_SIZE as usize], 0);
+    assert_eq!(heap[5], 5);
+    println!("brk_test passed!");
+    0
+}
@@ -0,0 +1,23 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{read, write};
+
+const STDIN: usize = 0;
+const STDOUT: usize = 1;
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let mut buffer = [0u8; 64];
+    loop {
+        let len = read(STDIN, &mut buffer);
+        if len <= 0 {
+            break;
+        }
+        write(STDOUT, &buffer[..len as usize]);
+    }
+    0
+}
@@ -0,0 +1,47 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{close, fork, pipe, read, wait, write};
+
+const LEN: usize = 8192;
+static mut DATA: [u8; LEN] = [1; LEN];
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let mut pipe_fd = [0usize; 2];
+    pipe(&mut pipe_fd);
+    let mut buf = [0u8; 4];
+    let pid = fork();
+    if pid == 0 {
+        // writes from the child must not show up in the parent
+        unsafe {
+            DATA[0] = 2;
+            DATA[LEN - 1] = 2;
+            assert_eq!(DATA[1], 1);
+        }
+        close(pipe_fd[0]);
+        write(pipe_fd[1], b"cow!");
+        close(pipe_fd[1]);
+        0
+    } else {
+        close(pipe_fd[1]);
+        // the kernel writing into buf must not touch the child's copy
+        assert_eq!(read(pipe_fd[0], &mut buf), 4);
+        assert_eq!(&buf, b"cow!");
+        close(pipe_fd[0]);
+        let mut exit_code: i32 = 0;
+        assert_eq!(pid, wait(&mut exit_code));
+        assert_eq!(exit_code, 0);
+        unsafe {
+            assert_eq!(DATA[0], 1);
+            assert_eq!(DATA[LEN - 1], 1);
+            DATA[0] = 3;
+            assert_eq!(DATA[0], 3);
+        }
+        println!("cow_test passed!");
+        0
+    }
+}
@@ -0,0 +1,16 @@
+#![no_std]
+#![no_main]
+
+extern crate alloc;
+
+#[macro_use]
+extern crate user_lib;
+
+use alloc::vec::Vec;
+
+#[no_mangle]
+pub fn main(_argc: usize, argv: &[&str]) -> i32 {
+    let args: Vec<&str> = argv.iter().skip(1).copied().collect();
+    println!("{}", args.join(" "));
+    0
+}
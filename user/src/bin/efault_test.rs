@@ -0,0 +1,50 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    exec, fork, fstat, getrlimit, mmap, mprotect, munmap, pipe, read, waitpid, write, RLimit, Stat, EFAULT,
+    PROT_READ, PROT_WRITE, RLIMIT_STACK,
+};
+
+const PAGE_SIZE: usize = 4096;
+const START: usize = 0x1000_0000;
+
+#[no_mangle]
+pub fn main() -> i32 {
+    // one unmapped page followed by a read only one
+    let prot = PROT_READ | PROT_WRITE;
+    assert_eq!(mmap(START, 2 * PAGE_SIZE, prot), START as isize);
+    assert_eq!(munmap(START, PAGE_SIZE), 0);
+    let unmapped = START;
+    let readonly = START + PAGE_SIZE;
+    assert_eq!(mprotect(readonly, PAGE_SIZE, PROT_READ), 0);
+
+    let bad = unsafe { core::slice::from_raw_parts_mut(unmapped as *mut u8, 16) };
+    assert_eq!(write(1, bad), EFAULT);
+    assert_eq!(read(0, bad), EFAULT);
+    // crossing from a good page into the unmapped one
+    let straddle = unsafe { core::slice::from_raw_parts((readonly - 8) as *const u8, 16) };
+    assert_eq!(write(1, straddle), EFAULT);
+    // readable is not enough to be written
+    let ro = unsafe { core::slice::from_raw_parts_mut(readonly as *mut u8, 16) };
+    assert_eq!(read(0, ro), EFAULT);
+    assert_eq!(fstat(1, unsafe { &mut *(readonly as *mut Stat) }), EFAULT);
+    assert_eq!(getrlimit(RLIMIT_STACK, unsafe { &mut *(unmapped as *mut RLimit) }), EFAULT);
+    assert_eq!(pipe(unsafe { core::slice::from_raw_parts_mut(readonly as *mut usize, 2) }), EFAULT);
+    let path = unsafe { core::str::from_utf8_unchecked(core::slice::from_raw_parts(unmapped as *const u8, 1)) };
+    assert_eq!(exec(path, &[core::ptr::null::<u8>()]), EFAULT);
+    // a bad exit code pointer does not reap the child
+    let pid = fork();
+    if pid == 0 {
+        return 7;
+    }
+    assert_eq!(waitpid(pid as usize, unsafe { &mut *(readonly as *mut i32) }), EFAULT);
+    let mut exit_code = 0;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, 7);
+    println!("efault_test passed!");
+    0
+}
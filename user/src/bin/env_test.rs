@@ -0,0 +1,38 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{execve, fork, getauxval, getenv, waitpid, AT_ENTRY, AT_PAGESZ, AT_PHNUM, AT_RANDOM};
+
+#[no_mangle]
+pub fn main(argc: usize, argv: &[&str]) -> i32 {
+    assert_eq!(getauxval(AT_PAGESZ), Some(4096));
+    assert!(getauxval(AT_ENTRY).unwrap() != 0);
+    assert!(getauxval(AT_PHNUM).unwrap() > 0);
+    let random = getauxval(AT_RANDOM).unwrap();
+    let bytes = unsafe { core::slice::from_raw_parts(random as *const u8, 16) };
+    assert!(bytes.iter().any(|b| *b != 0));
+    if argc == 2 && argv[1] == "child" {
+        // replaced environment
+        assert_eq!(getenv("PATH"), Some("/bin"));
+        assert_eq!(getenv("FOO"), Some("bar"));
+        assert_eq!(getenv("MISSING"), None);
+        return 0;
+    }
+    // inherited from initproc through every exec
+    assert_eq!(getenv("PATH"), Some("/"));
+    let pid = fork();
+    if pid == 0 {
+        let args = ["env_test\0".as_ptr(), "child\0".as_ptr(), core::ptr::null::<u8>()];
+        let envs = ["PATH=/bin\0".as_ptr(), "FOO=bar\0".as_ptr(), core::ptr::null::<u8>()];
+        execve("env_test\0", &args, &envs);
+        panic!("unreachable!");
+    }
+    let mut exit_code: i32 = 0;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, 0);
+    println!("env_test passed!");
+    0
+}
@@ -0,0 +1,72 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use core::arch::asm;
+use user_lib::{
+    close, exec, open, read, write, E2BIG, EINVAL, ENAMETOOLONG, ENOENT, ENOEXEC, ENOSYS, O_CREATE,
+    O_RDONLY, O_TRUNC, O_WRONLY,
+};
+
+// 20 of these do not fit the 64 KiB initial stack
+static LONG_ARG: [u8; 4000] = {
+    let mut arg = [b'a'; 4000];
+    arg[3999] = 0;
+    arg
+};
+
+// longer than any path the kernel reads
+static LONG_PATH: [u8; 5000] = {
+    let mut path = [b'a'; 5000];
+    path[4999] = 0;
+    path
+};
+
+const SYSCALL_OPEN: usize = 56;
+
+// not valid UTF-8, so not a path
+static BAD_PATH: [u8; 3] = [b'a', 0xff, 0];
+
+fn raw_syscall(id: usize, args: [usize; 3]) -> isize {
+    let mut ret: isize;
+    unsafe {
+        asm!("ecall", inlateout("x10") args[0] => ret, in("x11") args[1], in("x12") args[2], in("x17") id);
+    }
+    ret
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    // unknown ids fail instead of bringing the kernel down
+    assert_eq!(raw_syscall(999, [0; 3]), ENOSYS);
+    assert_eq!(raw_syscall(usize::MAX, [0; 3]), ENOSYS);
+    let no_args = [core::ptr::null::<u8>()];
+    assert_eq!(exec("no_such_app\0", &no_args), ENOENT);
+    // exec checks the image before giving up the current one
+    let fd = open("errno_test_file\0", O_CREATE | O_TRUNC | O_WRONLY);
+    assert!(fd > 2);
+    assert_eq!(write(fd as usize, b"not an elf"), 10);
+    close(fd as usize);
+    assert_eq!(exec("errno_test_file\0", &no_args), ENOEXEC);
+    let mut long_args = [LONG_ARG.as_ptr(); 21];
+    long_args[20] = core::ptr::null();
+    assert_eq!(exec("errno_test\0", &long_args), E2BIG);
+    let long_path = core::str::from_utf8(&LONG_PATH).unwrap();
+    assert_eq!(open(long_path, O_RDONLY), ENAMETOOLONG);
+    // paths are UTF-8, other bytes do not make a name
+    let bad_open = [BAD_PATH.as_ptr() as usize, O_RDONLY as usize, 0];
+    assert_eq!(raw_syscall(SYSCALL_OPEN, bad_open), EINVAL);
+    let fd = open("héllo\0", O_CREATE | O_TRUNC | O_WRONLY);
+    assert!(fd > 2);
+    assert_eq!(write(fd as usize, "é".as_bytes()), 2);
+    close(fd as usize);
+    let fd = open("héllo\0", O_RDONLY);
+    let mut buf = [0u8; 4];
+    assert_eq!(read(fd as usize, &mut buf), 2);
+    assert_eq!(&buf[..2], "é".as_bytes());
+    close(fd as usize);
+    println!("errno_test passed!");
+    0
+}
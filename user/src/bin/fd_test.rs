@@ -0,0 +1,61 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    Stat, close, dup, fstat, open, read, write, EBADF, EMFILE, ENOENT, O_CREATE, O_RDONLY, O_TRUNC,
+    O_WRONLY,
+};
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let fd = open("hello_world\0", O_RDONLY);
+    assert!(fd > 2);
+    let mut magic = [0u8; 4];
+    assert_eq!(read(fd as usize, &mut magic), 4);
+    assert_eq!(magic, [0x7f, b'E', b'L', b'F']);
+    let mut stat = Stat::default();
+    assert_eq!(fstat(fd as usize, &mut stat), 0);
+    assert!(stat.size > 4);
+    let new_fd = dup(fd as usize);
+    assert!(new_fd > fd);
+    assert_eq!(close(fd as usize), 0);
+    assert_eq!(close(fd as usize), EBADF);
+    assert_eq!(read(fd as usize, &mut magic), EBADF);
+    assert_eq!(close(new_fd as usize), 0);
+    assert_eq!(open("no_such_app\0", O_RDONLY), ENOENT);
+    let fd = open("fd_test_file\0", O_CREATE | O_TRUNC | O_WRONLY);
+    assert!(fd > 2);
+    assert_eq!(write(fd as usize, b"lOSe"), 4);
+    assert_eq!(read(fd as usize, &mut magic), EBADF);
+    close(fd as usize);
+    let fd = open("fd_test_file\0", O_RDONLY);
+    assert_eq!(read(fd as usize, &mut magic), 4);
+    assert_eq!(&magic, b"lOSe");
+    assert_eq!(read(fd as usize, &mut magic), 0);
+    close(fd as usize);
+    assert_eq!(write(100, b"unused"), EBADF);
+    let out = dup(1);
+    assert!(out > 2);
+    write(out as usize, b"write through dup'd stdout\n");
+    close(out as usize);
+    // the fd table is bounded, freed fds are handed out again
+    let mut last = 0;
+    loop {
+        let fd = dup(1);
+        if fd < 0 {
+            assert_eq!(fd, EMFILE);
+            break;
+        }
+        last = fd;
+    }
+    for fd in 3..=last {
+        close(fd as usize);
+    }
+    assert_eq!(dup(1), 3);
+    close(3);
+    println!("fd_test passed!");
+    0
+}
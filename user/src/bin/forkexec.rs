@@ -16,7 +16,7 @@ pub fn main() -> i32 {
             "pid {}: forked child start execing hello_world app ... ",
             getpid()
         );
-        exec("hello_world");
+        exec("hello_world\0", &["hello_world\0".as_ptr(), core::ptr::null::<u8>()]);
         100
     } else {
         // parent process
@@ -0,0 +1,62 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use core::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+use user_lib::{
+    exit, futex_wait, futex_wake, thread_create, waittid, yield_, FutexMutex, EAGAIN,
+};
+
+const THREADS: usize = 4;
+const ROUNDS: usize = 50;
+
+static LOCK: FutexMutex = FutexMutex::new();
+static COUNTER: AtomicUsize = AtomicUsize::new(0);
+static FLAG: AtomicU32 = AtomicU32::new(0);
+
+// yielding inside the critical section makes the others contend and sleep
+extern "C" fn increment(_: usize) -> ! {
+    for _ in 0..ROUNDS {
+        LOCK.lock();
+        let value = COUNTER.load(Ordering::SeqCst);
+        yield_();
+        COUNTER.store(value + 1, Ordering::SeqCst);
+        LOCK.unlock();
+    }
+    exit(0);
+}
+
+extern "C" fn release(_: usize) -> ! {
+    for _ in 0..10 {
+        yield_();
+    }
+    FLAG.store(1, Ordering::SeqCst);
+    futex_wake(&FLAG, 1);
+    exit(0);
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    // the value changed already, so there is nothing to wait for
+    assert_eq!(futex_wait(&FLAG, 1), EAGAIN);
+    assert_eq!(futex_wake(&FLAG, 1), 0);
+
+    let tid = thread_create(release as usize, 0) as usize;
+    while FLAG.load(Ordering::SeqCst) == 0 {
+        futex_wait(&FLAG, 0);
+    }
+    assert_eq!(waittid(tid), 0);
+
+    let mut tids = [0usize; THREADS];
+    for tid in tids.iter_mut() {
+        *tid = thread_create(increment as usize, 0) as usize;
+    }
+    for tid in tids.iter() {
+        assert_eq!(waittid(*tid), 0);
+    }
+    assert_eq!(COUNTER.load(Ordering::SeqCst), THREADS * ROUNDS);
+    println!("futex_test passed!");
+    0
+}
@@ -10,12 +10,12 @@ use user_lib::{exec, fork, wait, yield_};
 fn main() -> i32 {
     println!("Initproc online");
     if fork() == 0 {
-        exec("user_shell\0");
+        exec("user_shell\0", &["user_shell\0".as_ptr(), core::ptr::null::<u8>()]);
     } else {
         loop {
             let mut exit_code: i32 = 0;
             let pid = wait(&mut exit_code);
-            if pid == -1 {
+            if pid < 0 {
                 yield_();
                 continue;
             }
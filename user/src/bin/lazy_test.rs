@@ -0,0 +1,35 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{close, pipe, read, write};
+
+const LEN: usize = 256 * 1024;
+// lives in bss, pages are only backed once touched
+static mut BIG: [u8; LEN] = [0; LEN];
+static mut UNTOUCHED: [u8; 4096] = [0; 4096];
+
+#[no_mangle]
+pub fn main() -> i32 {
+    unsafe {
+        for i in (0..LEN).step_by(4096 * 3) {
+            assert_eq!(BIG[i], 0);
+            BIG[i] = (i / 4096) as u8;
+        }
+        for i in (0..LEN).step_by(4096 * 3) {
+            assert_eq!(BIG[i], (i / 4096) as u8);
+        }
+        // the kernel writes into a page the program never touched
+        let mut pipe_fd = [0usize; 2];
+        pipe(&mut pipe_fd);
+        write(pipe_fd[1], b"lazy");
+        close(pipe_fd[1]);
+        assert_eq!(read(pipe_fd[0], &mut UNTOUCHED[100..104]), 4);
+        close(pipe_fd[0]);
+        assert_eq!(&UNTOUCHED[100..104], b"lazy");
+    }
+    println!("lazy_test passed!");
+    0
+}
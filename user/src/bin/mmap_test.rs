@@ -0,0 +1,58 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{fork, mmap, munmap, wait, EEXIST, EINVAL, PROT_NONE, PROT_READ, PROT_WRITE};
+
+const PAGE_SIZE: usize = 4096;
+const START: usize = 0x1000_0000;
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let prot = PROT_READ | PROT_WRITE;
+    assert_eq!(mmap(START, 3 * PAGE_SIZE, prot), START as isize);
+    let mem = unsafe { core::slice::from_raw_parts_mut(START as *mut u8, 3 * PAGE_SIZE) };
+    assert!(mem.iter().all(|b| *b == 0));
+    for (i, byte) in mem.iter_mut().enumerate() {
+        *byte = (i / PAGE_SIZE) as u8 + 1;
+    }
+    // bad requests
+    assert_eq!(mmap(START + PAGE_SIZE, PAGE_SIZE, prot), EEXIST);
+    assert_eq!(mmap(START + 3 * PAGE_SIZE + 1, PAGE_SIZE, prot), EINVAL);
+    assert_eq!(mmap(START + 4 * PAGE_SIZE, PAGE_SIZE, 1 << 3), EINVAL);
+    assert_eq!(mmap(START + 4 * PAGE_SIZE, 0, prot), EINVAL);
+    // mappings are inherited by fork
+    let pid = fork();
+    if pid == 0 {
+        assert_eq!(mem[2 * PAGE_SIZE], 3);
+        return 0;
+    }
+    let mut exit_code: i32 = 0;
+    assert_eq!(pid, wait(&mut exit_code));
+    assert_eq!(exit_code, 0);
+    // punch a hole in the middle, the rest stays
+    assert_eq!(munmap(START + PAGE_SIZE, PAGE_SIZE), 0);
+    assert_eq!(munmap(START + PAGE_SIZE, PAGE_SIZE), EINVAL);
+    assert_eq!(munmap(START, 2 * PAGE_SIZE), EINVAL);
+    assert_eq!(mem[0], 1);
+    assert_eq!(mem[2 * PAGE_SIZE], 3);
+    // the hole can be mapped again and comes back zeroed
+    assert_eq!(mmap(START + PAGE_SIZE, PAGE_SIZE, prot), (START + PAGE_SIZE) as isize);
+    assert_eq!(mem[PAGE_SIZE], 0);
+    assert_eq!(munmap(START, 3 * PAGE_SIZE), 0);
+    // PROT_NONE reserves the range, touching it is a segfault
+    assert_eq!(mmap(START, PAGE_SIZE, PROT_NONE), START as isize);
+    assert_eq!(mmap(START, PAGE_SIZE, prot), EEXIST);
+    let pid = fork();
+    if pid == 0 {
+        let _ = unsafe { (START as *const u8).read_volatile() };
+        return 0;
+    }
+    assert_eq!(pid, wait(&mut exit_code));
+    assert_eq!(exit_code, -2);
+    assert_eq!(munmap(START, PAGE_SIZE), 0);
+    println!("mmap_test passed!");
+    0
+}
@@ -0,0 +1,59 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{fork, mmap, mprotect, waitpid, EINVAL, ENOMEM, PROT_EXEC, PROT_NONE, PROT_READ, PROT_WRITE};
+
+const PAGE_SIZE: usize = 4096;
+const START: usize = 0x1000_0000;
+
+// li a0, 42; ret
+const CODE: [u32; 2] = [0x02a00513, 0x00008067];
+
+#[no_mangle]
+pub fn main() -> i32 {
+    assert_eq!(mmap(START, 2 * PAGE_SIZE, PROT_READ | PROT_WRITE), START as isize);
+    let mem = unsafe { core::slice::from_raw_parts_mut(START as *mut u32, 2 * PAGE_SIZE / 4) };
+    mem[0] = CODE[0];
+    mem[1] = CODE[1];
+    mem[PAGE_SIZE / 4] = 7;
+    // only the first page becomes code, the area is split
+    assert_eq!(mprotect(START, PAGE_SIZE, PROT_READ | PROT_EXEC), 0);
+    let jit: extern "C" fn() -> usize = unsafe { core::mem::transmute(START) };
+    assert_eq!(jit(), 42);
+    mem[PAGE_SIZE / 4] = 8;
+    // writing to the code page now kills the writer
+    let pid = fork();
+    if pid == 0 {
+        mem[0] = 0;
+        return 0;
+    }
+    let mut exit_code: i32 = 0;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, -2);
+    // bad requests
+    assert_eq!(mprotect(START + 1, PAGE_SIZE, PROT_READ), EINVAL);
+    assert_eq!(mprotect(START, PAGE_SIZE, 1 << 3), EINVAL);
+    assert_eq!(mprotect(START, 3 * PAGE_SIZE, PROT_READ), ENOMEM);
+    // and back to writable
+    assert_eq!(mprotect(START, 2 * PAGE_SIZE, PROT_READ | PROT_WRITE), 0);
+    mem[0] = 1;
+    assert_eq!(mem[0], 1);
+    assert_eq!(mem[PAGE_SIZE / 4], 8);
+    // PROT_NONE takes all access away, reads fault too
+    assert_eq!(mprotect(START + PAGE_SIZE, PAGE_SIZE, PROT_NONE), 0);
+    let pid = fork();
+    if pid == 0 {
+        let _ = unsafe { ((START + PAGE_SIZE) as *const u32).read_volatile() };
+        return 0;
+    }
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, -2);
+    // and the contents are still there once access is back
+    assert_eq!(mprotect(START + PAGE_SIZE, PAGE_SIZE, PROT_READ), 0);
+    assert_eq!(mem[PAGE_SIZE / 4], 8);
+    println!("mprotect_test passed!");
+    0
+}
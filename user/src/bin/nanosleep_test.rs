@@ -0,0 +1,31 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{TimeSpec, exit, fork, get_time, kill, nanosleep, sleep, waitpid, EINVAL, SIGKILL};
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let start = get_time();
+    let req = TimeSpec { sec: 0, nsec: 200_000_000 };
+    assert_eq!(nanosleep(&req), 0);
+    let delta = get_time() - start;
+    assert!(delta >= 200, "woke up after {}ms", delta);
+    let bad = TimeSpec { sec: 0, nsec: 1_000_000_000 };
+    assert_eq!(nanosleep(&bad), EINVAL);
+    // a sleep too long to count in ticks lasts until something ends it
+    let pid = fork();
+    if pid == 0 {
+        nanosleep(&TimeSpec { sec: usize::MAX, nsec: 0 });
+        exit(1);
+    }
+    sleep(100);
+    assert_eq!(kill(pid as usize, SIGKILL), 0);
+    let mut exit_code = 0;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, -(SIGKILL as i32));
+    println!("nanosleep_test passed, slept {}ms!", delta);
+    0
+}
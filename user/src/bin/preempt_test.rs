@@ -0,0 +1,30 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{fork, get_time, wait, yield_};
+
+// 1s, far longer than a time slice
+const SPIN: isize = 1000;
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let deadline = get_time() + SPIN;
+    let pid = fork();
+    if pid == 0 {
+        // never gives up the cpu by itself
+        while get_time() < deadline {}
+        0
+    } else {
+        yield_();
+        let back = get_time();
+        let mut exit_code: i32 = 0;
+        assert_eq!(pid, wait(&mut exit_code));
+        assert_eq!(exit_code, 0);
+        assert!(back < deadline, "child was never preempted");
+        println!("preempt_test passed!");
+        0
+    }
+}
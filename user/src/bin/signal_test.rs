@@ -0,0 +1,160 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+use user_lib::{
+    close, exit, fork, getpid, kill, pipe, read, semaphore_create, semaphore_down, sig_bit, sigaction,
+    sigprocmask, sleep, thread_create, waitpid, waittid, write, yield_, SignalAction, EINTR, EINVAL,
+    EPIPE, ESRCH, SIGKILL, SIGPIPE, SIGSEGV, SIGTERM, SIGUSR1, SIGUSR2, SIG_BLOCK, SIG_DFL, SIG_IGN,
+    SIG_SETMASK, SIG_UNBLOCK,
+};
+
+static CAUGHT: AtomicUsize = AtomicUsize::new(0);
+
+extern "C" fn record(signum: usize) {
+    CAUGHT.fetch_or(1 << signum, Ordering::SeqCst);
+}
+
+extern "C" fn segv_exit(signum: usize) {
+    assert_eq!(signum, SIGSEGV);
+    exit(42);
+}
+
+extern "C" fn thread_exit(_: usize) {
+    exit(7);
+}
+
+// takes SIGUSR2 itself, the main thread blocks it
+extern "C" fn exit_in_handler(_: usize) -> ! {
+    let set = sig_bit(SIGUSR2);
+    assert_eq!(sigprocmask(SIG_UNBLOCK, Some(&set), None), 0);
+    kill(getpid() as usize, SIGUSR2);
+    unreachable!();
+}
+
+fn handle(signum: usize, handler: usize) {
+    let action = SignalAction { handler, ..Default::default() };
+    assert_eq!(sigaction(signum, Some(&action), None), 0);
+}
+
+fn caught(signum: usize) -> bool {
+    CAUGHT.load(Ordering::SeqCst) & (1 << signum) != 0
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let pid = getpid() as usize;
+    // the handler runs on the way back from kill, then kill returns as usual
+    handle(SIGUSR1, record as usize);
+    assert_eq!(kill(pid, SIGUSR1), 0);
+    assert!(caught(SIGUSR1));
+    let mut old = SignalAction::default();
+    assert_eq!(sigaction(SIGUSR1, None, Some(&mut old)), 0);
+    assert_eq!(old.handler, record as usize);
+
+    // blocked signals wait until they are unblocked
+    handle(SIGUSR2, record as usize);
+    let set = sig_bit(SIGUSR2);
+    assert_eq!(sigprocmask(SIG_SETMASK, Some(&set), None), 0);
+    assert_eq!(kill(pid, SIGUSR2), 0);
+    assert!(!caught(SIGUSR2));
+    assert_eq!(sigprocmask(SIG_UNBLOCK, Some(&set), None), 0);
+    assert!(caught(SIGUSR2));
+
+    // masks and running handlers belong to threads, one that exits
+    // inside its handler leaves the others alone
+    assert_eq!(sigprocmask(SIG_BLOCK, Some(&set), None), 0);
+    handle(SIGUSR2, thread_exit as usize);
+    let tid = thread_create(exit_in_handler as usize, 0);
+    assert!(tid > 0);
+    assert_eq!(waittid(tid as usize), 7);
+    CAUGHT.store(0, Ordering::SeqCst);
+    handle(SIGUSR2, record as usize);
+    assert_eq!(sigprocmask(SIG_UNBLOCK, Some(&set), None), 0);
+    assert_eq!(kill(pid, SIGUSR2), 0);
+    assert!(caught(SIGUSR2));
+
+    // ignored
+    handle(SIGTERM, SIG_IGN);
+    assert_eq!(kill(pid, SIGTERM), 0);
+
+    assert_eq!(sigaction(SIGKILL, Some(&SignalAction::default()), None), EINVAL);
+    assert_eq!(kill(usize::MAX - 1, SIGTERM), ESRCH);
+    assert_eq!(kill(pid, 64), EINVAL);
+
+    // the child inherits SIG_IGN, back to the default action which terminates
+    let child = fork();
+    if child == 0 {
+        handle(SIGTERM, 0);
+        loop {
+            yield_();
+        }
+    }
+    assert_eq!(kill(child as usize, SIGTERM), 0);
+    let mut exit_code = 0;
+    assert_eq!(waitpid(child as usize, &mut exit_code), child);
+    assert_eq!(exit_code, -(SIGTERM as i32));
+
+    // faults become SIGSEGV, which can be caught
+    let child = fork();
+    if child == 0 {
+        handle(SIGSEGV, segv_exit as usize);
+        unsafe {
+            (0x10 as *mut u8).write_volatile(1);
+        }
+        unreachable!();
+    }
+    assert_eq!(waitpid(child as usize, &mut exit_code), child);
+    assert_eq!(exit_code, 42);
+
+    // SIGKILL gets a child out of a wait nobody would end
+    let child = fork();
+    if child == 0 {
+        let semaphore = semaphore_create(0) as usize;
+        semaphore_down(semaphore);
+        unreachable!();
+    }
+    sleep(50);
+    assert_eq!(kill(child as usize, SIGKILL), 0);
+    assert_eq!(waitpid(child as usize, &mut exit_code), child);
+    assert_eq!(exit_code, -(SIGKILL as i32));
+
+    // a caught signal makes a blocking read fail with EINTR after the handler ran
+    let mut pipe_fd = [0usize; 2];
+    assert_eq!(pipe(&mut pipe_fd), 0);
+    let child = fork();
+    if child == 0 {
+        close(pipe_fd[1]);
+        CAUGHT.store(0, Ordering::SeqCst);
+        let mut buf = [0u8; 1];
+        let result = read(pipe_fd[0], &mut buf);
+        exit(if result == EINTR && caught(SIGUSR1) { 0 } else { 1 });
+    }
+    close(pipe_fd[0]);
+    sleep(50);
+    assert_eq!(kill(child as usize, SIGUSR1), 0);
+    close(pipe_fd[1]);
+    assert_eq!(waitpid(child as usize, &mut exit_code), child);
+    assert_eq!(exit_code, 0);
+
+    // writing with no reader left raises SIGPIPE, which kills by default
+    assert_eq!(pipe(&mut pipe_fd), 0);
+    close(pipe_fd[0]);
+    let child = fork();
+    if child == 0 {
+        write(pipe_fd[1], b"lost");
+        exit(0);
+    }
+    assert_eq!(waitpid(child as usize, &mut exit_code), child);
+    assert_eq!(exit_code, -(SIGPIPE as i32));
+    // and with it ignored the write fails with EPIPE
+    handle(SIGPIPE, SIG_IGN);
+    assert_eq!(write(pipe_fd[1], b"lost"), EPIPE);
+    handle(SIGPIPE, SIG_DFL);
+    close(pipe_fd[1]);
+    println!("signal_test passed!");
+    0
+}
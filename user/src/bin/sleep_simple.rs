@@ -14,7 +14,7 @@ pub fn main() -> i32 {
     sleep(100);
     let end = get_time();
     println!(
-        "time_msec = {} after sleeping 100 ticks, delta = {}ms!",
+        "time_msec = {} after sleeping 100 msecs, delta = {}ms!",
         end,
         end - start
     );
@@ -0,0 +1,111 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use core::arch::asm;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use user_lib::{
+    exit, fork, getpid, mmap, munmap, thread_create, waitpid, waittid, yield_, FutexMutex,
+    PROT_READ, PROT_WRITE,
+};
+
+const THREADS: usize = 4;
+const ROUNDS: usize = 10000;
+const PAGE_SIZE: usize = 4096;
+const START: usize = 0x1000_0000;
+
+static LOCK: FutexMutex = FutexMutex::new();
+static LOCKED_COUNTER: AtomicUsize = AtomicUsize::new(0);
+static ATOMIC_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+// no yields, with several harts the threads really run side by side
+extern "C" fn increment(_: usize) -> ! {
+    for _ in 0..ROUNDS {
+        ATOMIC_COUNTER.fetch_add(1, Ordering::SeqCst);
+        LOCK.lock();
+        let value = LOCKED_COUNTER.load(Ordering::Relaxed);
+        LOCKED_COUNTER.store(value + 1, Ordering::Relaxed);
+        LOCK.unlock();
+    }
+    exit(0);
+}
+
+extern "C" fn spin(_: usize) -> ! {
+    loop {
+        ATOMIC_COUNTER.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+// once the page is unmapped the writer must fault, even if it runs on another hart
+extern "C" fn write_forever(_: usize) -> ! {
+    let page = START as *mut usize;
+    loop {
+        unsafe { page.write_volatile(page.read_volatile() + 1) };
+    }
+}
+
+// tp belongs to the program, the kernel must neither trust nor clobber it
+fn tp_survives_traps() -> bool {
+    let saved: usize;
+    let tp: usize;
+    unsafe {
+        asm!("mv {}, tp", out(reg) saved);
+        asm!("mv tp, {}", in(reg) usize::MAX);
+    }
+    for _ in 0..100 {
+        yield_();
+        getpid();
+    }
+    unsafe {
+        asm!("mv {}, tp", out(reg) tp);
+        asm!("mv tp, {}", in(reg) saved);
+    }
+    tp == usize::MAX
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    assert!(tp_survives_traps());
+    let mut tids = [0usize; THREADS];
+    for tid in tids.iter_mut() {
+        *tid = thread_create(increment as usize, 0) as usize;
+    }
+    for tid in tids.iter() {
+        assert_eq!(waittid(*tid), 0);
+    }
+    assert_eq!(ATOMIC_COUNTER.load(Ordering::SeqCst), THREADS * ROUNDS);
+    assert_eq!(LOCKED_COUNTER.load(Ordering::SeqCst), THREADS * ROUNDS);
+
+    // the exit has to stop threads that keep running on other harts
+    let pid = fork();
+    if pid == 0 {
+        for _ in 0..THREADS {
+            assert!(thread_create(spin as usize, 0) > 0);
+        }
+        exit(7);
+    }
+    let mut exit_code = 0;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, 7);
+
+    let pid = fork();
+    if pid == 0 {
+        assert_eq!(mmap(START, PAGE_SIZE, PROT_READ | PROT_WRITE), START as isize);
+        let page = START as *mut usize;
+        unsafe { page.write_volatile(0) };
+        assert!(thread_create(write_forever as usize, 0) > 0);
+        while unsafe { page.read_volatile() } == 0 {
+            yield_();
+        }
+        assert_eq!(munmap(START, PAGE_SIZE), 0);
+        loop {
+            yield_();
+        }
+    }
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, -2);
+    println!("smp_test passed!");
+    0
+}
@@ -0,0 +1,57 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{RLimit, fork, getrlimit, setrlimit, waitpid, EINVAL, EPERM, RLIMIT_STACK};
+
+// each level keeps 1 KiB alive on the stack
+fn deep(depth: usize) -> usize {
+    let mut frame = [0u8; 1024];
+    unsafe {
+        core::ptr::write_volatile(&mut frame[depth % 1024], 1);
+    }
+    let below = if depth == 0 { 0 } else { deep(depth - 1) };
+    below + unsafe { core::ptr::read_volatile(&frame[depth % 1024]) } as usize
+}
+
+// one frame larger than the initial 64 KiB stack, its lowest page is touched first
+#[inline(never)]
+fn wide() -> u8 {
+    let mut frame = [0u8; 128 * 1024];
+    unsafe {
+        core::ptr::write_volatile(&mut frame[0], 1);
+        core::ptr::write_volatile(&mut frame[frame.len() - 1], 1);
+        core::ptr::read_volatile(&frame[0]) + core::ptr::read_volatile(&frame[frame.len() - 1])
+    }
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let mut rlimit = RLimit::default();
+    assert_eq!(getrlimit(RLIMIT_STACK, &mut rlimit), 0);
+    assert!(rlimit.cur >= 1024 * 1024);
+    assert_eq!(wide(), 2);
+    // about 1 MiB, far beyond the initial 64 KiB stack
+    assert_eq!(deep(1000), 1001);
+    // cur above max is refused
+    let bad = RLimit { cur: rlimit.max + 1, max: rlimit.max };
+    assert_eq!(setrlimit(RLIMIT_STACK, &bad), EINVAL);
+    // so is raising max
+    let raised = RLimit { cur: rlimit.cur, max: rlimit.max + 4096 };
+    assert_eq!(setrlimit(RLIMIT_STACK, &raised), EPERM);
+    let pid = fork();
+    if pid == 0 {
+        let small = RLimit { cur: 256 * 1024, max: rlimit.max };
+        assert_eq!(setrlimit(RLIMIT_STACK, &small), 0);
+        // the stack already grew past the limit in the parent, go further
+        deep(2000);
+        return 0;
+    }
+    let mut exit_code: i32 = 0;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, -2);
+    println!("stack_grow_test passed!");
+    0
+}
@@ -0,0 +1,105 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use user_lib::{
+    condvar_create, condvar_signal, condvar_wait, exit, mutex_create, mutex_lock, mutex_unlock,
+    semaphore_create, semaphore_down, semaphore_up, thread_create, waittid, yield_, EINVAL, EPERM,
+};
+
+const THREADS: usize = 4;
+const ROUNDS: usize = 50;
+
+static MUTEX: AtomicUsize = AtomicUsize::new(0);
+static SEMAPHORE: AtomicUsize = AtomicUsize::new(0);
+static CONDVAR: AtomicUsize = AtomicUsize::new(0);
+static COUNTER: AtomicUsize = AtomicUsize::new(0);
+static READY: AtomicBool = AtomicBool::new(false);
+
+// a read, yield, write increment only adds up when the mutex keeps others out
+extern "C" fn increment(_: usize) -> ! {
+    let mutex = MUTEX.load(Ordering::SeqCst);
+    for _ in 0..ROUNDS {
+        assert_eq!(mutex_lock(mutex), 0);
+        let value = COUNTER.load(Ordering::SeqCst);
+        yield_();
+        COUNTER.store(value + 1, Ordering::SeqCst);
+        assert_eq!(mutex_unlock(mutex), 0);
+    }
+    exit(0);
+}
+
+// the mutex belongs to the main thread
+extern "C" fn foreign_unlock(_: usize) -> ! {
+    assert_eq!(mutex_unlock(MUTEX.load(Ordering::SeqCst)), EPERM);
+    exit(0);
+}
+
+extern "C" fn post(_: usize) -> ! {
+    for _ in 0..10 {
+        yield_();
+    }
+    READY.store(true, Ordering::SeqCst);
+    semaphore_up(SEMAPHORE.load(Ordering::SeqCst));
+    exit(0);
+}
+
+extern "C" fn notify(_: usize) -> ! {
+    let mutex = MUTEX.load(Ordering::SeqCst);
+    yield_();
+    mutex_lock(mutex);
+    READY.store(true, Ordering::SeqCst);
+    condvar_signal(CONDVAR.load(Ordering::SeqCst));
+    mutex_unlock(mutex);
+    exit(0);
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let mutex = mutex_create();
+    assert!(mutex >= 0);
+    MUTEX.store(mutex as usize, Ordering::SeqCst);
+    assert_eq!(mutex_unlock(mutex as usize), EPERM);
+    assert_eq!(mutex_lock(1000), EINVAL);
+    let mut tids = [0usize; THREADS];
+    for tid in tids.iter_mut() {
+        *tid = thread_create(increment as usize, 0) as usize;
+    }
+    for tid in tids.iter() {
+        assert_eq!(waittid(*tid), 0);
+    }
+    assert_eq!(COUNTER.load(Ordering::SeqCst), THREADS * ROUNDS);
+    assert_eq!(mutex_lock(mutex as usize), 0);
+    let tid = thread_create(foreign_unlock as usize, 0) as usize;
+    assert_eq!(waittid(tid), 0);
+    assert_eq!(mutex_unlock(mutex as usize), 0);
+
+    // down blocks until the other thread posts
+    let semaphore = semaphore_create(0);
+    assert!(semaphore >= 0);
+    SEMAPHORE.store(semaphore as usize, Ordering::SeqCst);
+    let tid = thread_create(post as usize, 0) as usize;
+    assert_eq!(semaphore_down(semaphore as usize), 0);
+    assert!(READY.load(Ordering::SeqCst));
+    assert_eq!(waittid(tid), 0);
+    assert_eq!(semaphore_down(1000), EINVAL);
+
+    READY.store(false, Ordering::SeqCst);
+    let condvar = condvar_create();
+    assert!(condvar >= 0);
+    CONDVAR.store(condvar as usize, Ordering::SeqCst);
+    // waiting needs the mutex
+    assert_eq!(condvar_wait(condvar as usize, mutex as usize), EPERM);
+    let tid = thread_create(notify as usize, 0) as usize;
+    mutex_lock(mutex as usize);
+    while !READY.load(Ordering::SeqCst) {
+        assert_eq!(condvar_wait(condvar as usize, mutex as usize), 0);
+    }
+    mutex_unlock(mutex as usize);
+    assert_eq!(waittid(tid), 0);
+    println!("sync_test passed!");
+    0
+}
@@ -0,0 +1,103 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use user_lib::{exit, fork, gettid, thread_create, waitpid, waittid, yield_, EDEADLK, ESRCH};
+
+const THREADS: usize = 4;
+
+static COUNTER: AtomicUsize = AtomicUsize::new(0);
+static TIDS: [AtomicUsize; THREADS] = [
+    AtomicUsize::new(0),
+    AtomicUsize::new(0),
+    AtomicUsize::new(0),
+    AtomicUsize::new(0),
+];
+static STACKS: [AtomicUsize; THREADS] = [
+    AtomicUsize::new(0),
+    AtomicUsize::new(0),
+    AtomicUsize::new(0),
+    AtomicUsize::new(0),
+];
+
+static RELEASE: AtomicBool = AtomicBool::new(false);
+static PARKED_STACK: AtomicUsize = AtomicUsize::new(0);
+
+// with record set it notes where its stack is and waits for RELEASE,
+// otherwise it exits with 1 if its stack is at the noted place
+extern "C" fn parked(record: usize) -> ! {
+    let local = 0usize;
+    if record == 1 {
+        PARKED_STACK.store(&local as *const usize as usize, Ordering::SeqCst);
+        while !RELEASE.load(Ordering::SeqCst) {
+            yield_();
+        }
+        exit(0);
+    }
+    exit((PARKED_STACK.load(Ordering::SeqCst) == &local as *const usize as usize) as i32);
+}
+
+extern "C" fn worker(idx: usize) -> ! {
+    let local = idx;
+    STACKS[idx].store(&local as *const usize as usize, Ordering::SeqCst);
+    TIDS[idx].store(gettid() as usize, Ordering::SeqCst);
+    for _ in 0..100 {
+        COUNTER.fetch_add(1, Ordering::SeqCst);
+        yield_();
+    }
+    exit(100 + idx as i32);
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    assert_eq!(gettid(), 0);
+    let mut tids = [0usize; THREADS];
+    for (idx, tid) in tids.iter_mut().enumerate() {
+        let ret = thread_create(worker as usize, idx);
+        assert!(ret > 0);
+        *tid = ret as usize;
+    }
+    // the threads share our memory, so their progress is visible here
+    for (idx, tid) in tids.iter().enumerate() {
+        assert_eq!(waittid(*tid), 100 + idx as isize);
+        assert_eq!(TIDS[idx].load(Ordering::SeqCst), *tid);
+    }
+    assert_eq!(COUNTER.load(Ordering::SeqCst), THREADS * 100);
+    // every thread ran on a stack of its own
+    for i in 0..THREADS {
+        for j in i + 1..THREADS {
+            assert_ne!(STACKS[i].load(Ordering::SeqCst), STACKS[j].load(Ordering::SeqCst));
+        }
+    }
+    // reaped threads are gone, and nobody can wait for itself
+    assert_eq!(waittid(tids[0]), ESRCH);
+    assert_eq!(waittid(0), EDEADLK);
+    assert_eq!(waittid(1000), ESRCH);
+    // reaped tids can be handed out again
+    let tid = thread_create(worker as usize, 0);
+    assert!(tid > 0);
+    assert_eq!(waittid(tid as usize), 100);
+    // a child forked next to another thread only gets the forking one, so the
+    // other's stack slot is free again for the first thread it creates
+    let tid = thread_create(parked as usize, 1);
+    assert!(tid > 0);
+    while PARKED_STACK.load(Ordering::SeqCst) == 0 {
+        yield_();
+    }
+    let pid = fork();
+    if pid == 0 {
+        let tid = thread_create(parked as usize, 0);
+        assert!(tid > 0);
+        exit(waittid(tid as usize) as i32);
+    }
+    let mut exit_code = 0;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, 1);
+    RELEASE.store(true, Ordering::SeqCst);
+    assert_eq!(waittid(tid as usize), 0);
+    println!("thread_test passed!");
+    0
+}
@@ -13,8 +13,9 @@ const DL: u8 = 0x7fu8;
 const BS: u8 = 0x08u8;
 
 use alloc::string::String;
+use alloc::vec::Vec;
 use user_lib::console::getchar;
-use user_lib::{exec, fork, waitpid,shutdown};
+use user_lib::{close, dup, exec, fork, pipe, waitpid, shutdown};
 
 #[no_mangle]
 pub fn main() -> i32 {
@@ -30,15 +31,64 @@ pub fn main() -> i32 {
                     if line.as_str().eq("shutdown") {
                         shutdown()
                     }
-                    line.push('\0');
-                    let pid = fork();
-                    if pid == 0 {
-                        if exec(line.as_str()) == -1 {
-                            println!("Error when executing!");
-                            return -4;
+                    // each command is split into nul terminated args, args[0] is the program
+                    let cmds: Vec<Vec<String>> = line
+                        .split('|')
+                        .map(|cmd| {
+                            cmd.split_whitespace()
+                                .map(|arg| {
+                                    let mut arg = String::from(arg);
+                                    arg.push('\0');
+                                    arg
+                                })
+                                .collect()
+                        })
+                        .collect();
+                    if cmds.iter().any(|cmd| cmd.is_empty()) {
+                        println!("Invalid pipeline!");
+                        line.clear();
+                        print!(">> ");
+                        continue;
+                    }
+                    // pipes[i] connects cmds[i] to cmds[i + 1]
+                    let mut pipes: Vec<[usize; 2]> = Vec::new();
+                    for _ in 1..cmds.len() {
+                        let mut pipe_fd = [0usize; 2];
+                        pipe(&mut pipe_fd);
+                        pipes.push(pipe_fd);
+                    }
+                    let mut pids: Vec<isize> = Vec::new();
+                    for (i, cmd) in cmds.iter().enumerate() {
+                        let pid = fork();
+                        if pid == 0 {
+                            if i > 0 {
+                                close(0);
+                                assert_eq!(dup(pipes[i - 1][0]), 0);
+                            }
+                            if i < pipes.len() {
+                                close(1);
+                                assert_eq!(dup(pipes[i][1]), 1);
+                            }
+                            for pipe_fd in pipes.iter() {
+                                close(pipe_fd[0]);
+                                close(pipe_fd[1]);
+                            }
+                            let mut args_addr: Vec<*const u8> =
+                                cmd.iter().map(|arg| arg.as_ptr()).collect();
+                            args_addr.push(core::ptr::null::<u8>());
+                            if exec(cmd[0].as_str(), args_addr.as_slice()) < 0 {
+                                println!("Error when executing!");
+                                return -4;
+                            }
+                            unreachable!();
                         }
-                        unreachable!();
-                    } else {
+                        pids.push(pid);
+                    }
+                    for pipe_fd in pipes.iter() {
+                        close(pipe_fd[0]);
+                        close(pipe_fd[1]);
+                    }
+                    for pid in pids {
                         let mut exit_code: i32 = 0;
                         let exit_pid = waitpid(pid as usize, &mut exit_code);
                         assert_eq!(pid, exit_pid);
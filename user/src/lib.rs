@@ -0,0 +1,418 @@
+#![no_std]
+#![feature(linkage)]
+#![feature(panic_info_message)]
+#![feature(alloc_error_handler)]
+
+#[macro_use]
+pub mod console;
+mod lang_items;
+mod syscall;
+
+extern crate alloc;
+
+use alloc::vec::Vec;
+use core::alloc::{GlobalAlloc, Layout};
+use core::ptr::{null_mut, NonNull};
+use core::sync::atomic::{AtomicU32, Ordering};
+use buddy_system_allocator::LockedHeap;
+use syscall::*;
+
+// the least the heap grows by at a time
+const HEAP_GROW: usize = 16384;
+
+// a buddy heap that takes more memory from the kernel with sbrk when it runs out
+struct SbrkHeap(LockedHeap);
+
+unsafe impl GlobalAlloc for SbrkHeap {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let mut heap = self.0.lock();
+        loop {
+            if let Ok(ptr) = heap.alloc(layout) {
+                return ptr.as_ptr();
+            }
+            // twice the block size always holds an aligned block, the break stays page aligned
+            let size = (layout.size().max(layout.align()).next_power_of_two() * 2).max(HEAP_GROW);
+            let start = sbrk(size as isize);
+            if start < 0 {
+                return null_mut();
+            }
+            heap.add_to_heap(start as usize, start as usize + size);
+        }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        self.0.lock().dealloc(NonNull::new_unchecked(ptr), layout);
+    }
+}
+
+#[global_allocator]
+static HEAP: SbrkHeap = SbrkHeap(LockedHeap::empty());
+
+#[alloc_error_handler]
+pub fn handle_alloc_error(layout: core::alloc::Layout) -> ! {
+    panic!("Heap allocation error, layout = {:?}", layout);
+}
+
+#[no_mangle]
+#[link_section = ".text.entry"]
+pub extern "C" fn _start(argc: usize, argv: usize, envp: usize) -> ! {
+    unsafe {
+        ENVP = envp;
+    }
+    //the kernel leaves argc in a0, argv in a1 and envp in a2
+    let args: Vec<&'static str> = (0..argc).map(|i| c_str(array_word(argv, i))).collect();
+    exit(main(argc, args.as_slice()));
+}
+
+//null terminated environment array set up by the kernel
+static mut ENVP: usize = 0;
+
+fn array_word(array: usize, index: usize) -> usize {
+    unsafe { ((array + index * core::mem::size_of::<usize>()) as *const usize).read_volatile() }
+}
+
+fn c_str(ptr: usize) -> &'static str {
+    let len = (0usize..)
+        .find(|i| unsafe { ((ptr + *i) as *const u8).read_volatile() == 0 })
+        .unwrap();
+    core::str::from_utf8(unsafe { core::slice::from_raw_parts(ptr as *const u8, len) }).unwrap()
+}
+
+//"KEY=value" strings this program was started with
+pub fn environ() -> Vec<&'static str> {
+    let envp = unsafe { ENVP };
+    (0..).map(|i| array_word(envp, i)).take_while(|ptr| *ptr != 0).map(c_str).collect()
+}
+
+pub fn getenv(name: &str) -> Option<&'static str> {
+    environ().into_iter().find_map(|env| {
+        let (key, value) = env.split_once('=')?;
+        if key == name { Some(value) } else { None }
+    })
+}
+
+pub const AT_NULL: usize = 0;
+pub const AT_PHDR: usize = 3;
+pub const AT_PHENT: usize = 4;
+pub const AT_PHNUM: usize = 5;
+pub const AT_PAGESZ: usize = 6;
+pub const AT_ENTRY: usize = 9;
+pub const AT_RANDOM: usize = 25;
+
+//auxiliary vector entry, the pairs start right after envp's null
+pub fn getauxval(key: usize) -> Option<usize> {
+    let envp = unsafe { ENVP };
+    let mut i = (0..).find(|i| array_word(envp, *i) == 0).unwrap() + 1;
+    loop {
+        match array_word(envp, i) {
+            AT_NULL => return None,
+            k if k == key => return Some(array_word(envp, i + 1)),
+            _ => i += 2,
+        }
+    }
+}
+
+#[linkage = "weak"]
+#[no_mangle]
+fn main(_argc: usize, _argv: &[&str]) -> i32 {
+    panic!("Cannot find main!");
+}
+
+pub const O_RDONLY: u32 = 0;
+pub const O_WRONLY: u32 = 1 << 0;
+pub const O_RDWR: u32 = 1 << 1;
+pub const O_CREATE: u32 = 1 << 9;
+pub const O_TRUNC: u32 = 1 << 10;
+
+#[repr(C)]
+#[derive(Copy, Clone, Default)]
+pub struct Stat {
+    pub mode: u32,
+    pub size: usize,
+}
+
+// syscalls fail with a negated linux errno
+pub const EPERM: isize = -1;
+pub const ENOENT: isize = -2;
+pub const ESRCH: isize = -3;
+pub const EINTR: isize = -4;
+pub const E2BIG: isize = -7;
+pub const ENOEXEC: isize = -8;
+pub const EBADF: isize = -9;
+pub const ECHILD: isize = -10;
+pub const EAGAIN: isize = -11;
+pub const ENOMEM: isize = -12;
+pub const EFAULT: isize = -14;
+pub const EEXIST: isize = -17;
+pub const EINVAL: isize = -22;
+pub const EMFILE: isize = -24;
+pub const EPIPE: isize = -32;
+pub const EDEADLK: isize = -35;
+pub const ENAMETOOLONG: isize = -36;
+pub const ENOSYS: isize = -38;
+
+pub const PROT_NONE: usize = 0;
+pub const PROT_READ: usize = 1 << 0;
+pub const PROT_WRITE: usize = 1 << 1;
+pub const PROT_EXEC: usize = 1 << 2;
+
+pub const RLIMIT_STACK: usize = 3;
+
+#[repr(C)]
+#[derive(Copy, Clone, Default)]
+pub struct RLimit {
+    pub cur: usize,
+    pub max: usize,
+}
+
+pub const SIGHUP: usize = 1;
+pub const SIGINT: usize = 2;
+pub const SIGQUIT: usize = 3;
+pub const SIGILL: usize = 4;
+pub const SIGTRAP: usize = 5;
+pub const SIGABRT: usize = 6;
+pub const SIGBUS: usize = 7;
+pub const SIGFPE: usize = 8;
+pub const SIGKILL: usize = 9;
+pub const SIGUSR1: usize = 10;
+pub const SIGSEGV: usize = 11;
+pub const SIGUSR2: usize = 12;
+pub const SIGPIPE: usize = 13;
+pub const SIGALRM: usize = 14;
+pub const SIGTERM: usize = 15;
+pub const SIGCHLD: usize = 17;
+
+pub const SIG_DFL: usize = 0;
+pub const SIG_IGN: usize = 1;
+
+pub const SIG_BLOCK: usize = 0;
+pub const SIG_UNBLOCK: usize = 1;
+pub const SIG_SETMASK: usize = 2;
+
+#[repr(C)]
+#[derive(Copy, Clone, Default)]
+pub struct SignalAction {
+    // SIG_DFL, SIG_IGN or an extern "C" fn(signum: usize)
+    pub handler: usize,
+    // blocked on top of the signal itself while the handler runs
+    pub mask: usize,
+    // filled in by sigaction
+    pub restorer: usize,
+}
+
+pub fn sig_bit(signum: usize) -> usize {
+    1 << signum
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Default)]
+pub struct TimeSpec {
+    pub sec: usize,
+    pub nsec: usize,
+}
+
+pub fn dup(fd: usize) -> isize {
+    sys_dup(fd)
+}
+pub fn open(path: &str, flags: u32) -> isize {
+    sys_open(path, flags)
+}
+pub fn close(fd: usize) -> isize {
+    sys_close(fd)
+}
+pub fn fstat(fd: usize, stat: &mut Stat) -> isize {
+    sys_fstat(fd, stat as *mut _)
+}
+pub fn pipe(pipe_fd: &mut [usize]) -> isize {
+    sys_pipe(pipe_fd)
+}
+pub fn read(fd: usize, buf: &mut [u8]) -> isize {
+    sys_read(fd, buf)
+}
+pub fn write(fd: usize, buf: &[u8]) -> isize {
+    sys_write(fd, buf)
+}
+pub fn exit(exit_code: i32) -> ! {
+    sys_exit(exit_code);
+}
+pub fn yield_() -> isize {
+    sys_yield()
+}
+// milliseconds since boot
+pub fn get_time() -> isize {
+    sys_get_time_ms()
+}
+// raw mtime ticks
+pub fn get_time_ticks() -> isize {
+    sys_get_time()
+}
+// brk(0) returns the current break
+pub fn brk(new_brk: usize) -> isize {
+    sys_brk(new_brk)
+}
+// returns the old break, or -1 if the heap cannot move
+pub fn sbrk(increment: isize) -> isize {
+    let old_brk = sys_brk(0);
+    if increment != 0 && sys_brk((old_brk + increment) as usize) < 0 {
+        return -1;
+    }
+    old_brk
+}
+pub fn mmap(start: usize, len: usize, prot: usize) -> isize {
+    sys_mmap(start, len, prot)
+}
+pub fn munmap(start: usize, len: usize) -> isize {
+    sys_munmap(start, len)
+}
+pub fn mprotect(start: usize, len: usize, prot: usize) -> isize {
+    sys_mprotect(start, len, prot)
+}
+pub fn getrlimit(resource: usize, rlimit: &mut RLimit) -> isize {
+    sys_getrlimit(resource, rlimit)
+}
+pub fn setrlimit(resource: usize, rlimit: &RLimit) -> isize {
+    sys_setrlimit(resource, rlimit)
+}
+pub fn kill(pid: usize, signum: usize) -> isize {
+    sys_kill(pid, signum)
+}
+// handlers return here, the kernel then resumes the interrupted code
+extern "C" fn sigreturn_restorer() -> ! {
+    sys_sigreturn();
+    unreachable!();
+}
+pub fn sigaction(signum: usize, action: Option<&SignalAction>, old_action: Option<&mut SignalAction>) -> isize {
+    let action = action.map(|action| SignalAction {
+        restorer: sigreturn_restorer as usize,
+        ..*action
+    });
+    sys_sigaction(
+        signum,
+        action.as_ref().map_or(core::ptr::null(), |action| action as *const SignalAction),
+        old_action.map_or(core::ptr::null_mut(), |action| action as *mut SignalAction),
+    )
+}
+pub fn sigprocmask(how: usize, set: Option<&usize>, old_set: Option<&mut usize>) -> isize {
+    sys_sigprocmask(
+        how,
+        set.map_or(core::ptr::null(), |set| set as *const usize),
+        old_set.map_or(core::ptr::null_mut(), |set| set as *mut usize),
+    )
+}
+pub fn getpid() -> isize {
+    sys_getpid()
+}
+pub fn fork() -> isize {
+    sys_fork()
+}
+//keeps the current environment
+pub fn exec(path: &str, args: &[*const u8]) -> isize {
+    sys_exec(path, args, unsafe { ENVP } as *const *const u8)
+}
+pub fn execve(path: &str, args: &[*const u8], envs: &[*const u8]) -> isize {
+    sys_exec(path, args, envs.as_ptr())
+}
+pub fn shutdown() -> ! {
+    sys_shutdown();
+}
+
+// blocks in the kernel until a child exits
+pub fn wait(exit_code: &mut i32) -> isize {
+    sys_waitpid(-1, exit_code as *mut _)
+}
+
+pub fn waitpid(pid: usize, exit_code: &mut i32) -> isize {
+    sys_waitpid(pid as isize, exit_code as *mut _)
+}
+
+// the new thread calls entry(arg) on a stack of its own, entry must end with exit
+pub fn thread_create(entry: usize, arg: usize) -> isize {
+    sys_thread_create(entry, arg)
+}
+pub fn gettid() -> isize {
+    sys_gettid()
+}
+// blocks until thread tid exits, returns its exit code
+pub fn waittid(tid: usize) -> isize {
+    sys_waittid(tid)
+}
+
+pub fn mutex_create() -> isize {
+    sys_mutex_create()
+}
+pub fn mutex_lock(id: usize) -> isize {
+    sys_mutex_lock(id)
+}
+pub fn mutex_unlock(id: usize) -> isize {
+    sys_mutex_unlock(id)
+}
+pub fn semaphore_create(count: usize) -> isize {
+    sys_semaphore_create(count)
+}
+pub fn semaphore_up(id: usize) -> isize {
+    sys_semaphore_up(id)
+}
+pub fn semaphore_down(id: usize) -> isize {
+    sys_semaphore_down(id)
+}
+pub fn condvar_create() -> isize {
+    sys_condvar_create()
+}
+pub fn condvar_signal(id: usize) -> isize {
+    sys_condvar_signal(id)
+}
+// mutex_id must be held, it is held again when this returns. A signal
+// ends the wait early like a spurious wakeup
+pub fn condvar_wait(id: usize, mutex_id: usize) -> isize {
+    match sys_condvar_wait(id, mutex_id) {
+        EINTR => {
+            while mutex_lock(mutex_id) == EINTR {}
+            0
+        }
+        result => result,
+    }
+}
+
+pub const FUTEX_WAIT: usize = 0;
+pub const FUTEX_WAKE: usize = 1;
+
+// sleeps unless *word has already changed from val, then returns EAGAIN
+pub fn futex_wait(word: &AtomicU32, val: u32) -> isize {
+    sys_futex(word as *const AtomicU32 as *const u32, FUTEX_WAIT, val as usize)
+}
+// returns how many waiters were woken
+pub fn futex_wake(word: &AtomicU32, count: usize) -> isize {
+    sys_futex(word as *const AtomicU32 as *const u32, FUTEX_WAKE, count)
+}
+
+// mutex that only enters the kernel under contention. The word is 0 when
+// unlocked, 1 when locked and 2 when locked with possible waiters
+pub struct FutexMutex {
+    state: AtomicU32,
+}
+
+impl FutexMutex {
+    pub const fn new() -> Self {
+        FutexMutex { state: AtomicU32::new(0) }
+    }
+    pub fn lock(&self) {
+        if self.state.compare_exchange(0, 1, Ordering::Acquire, Ordering::Relaxed).is_ok() {
+            return;
+        }
+        while self.state.swap(2, Ordering::Acquire) != 0 {
+            futex_wait(&self.state, 2);
+        }
+    }
+    pub fn unlock(&self) {
+        if self.state.swap(0, Ordering::Release) == 2 {
+            futex_wake(&self.state, 1);
+        }
+    }
+}
+
+pub fn sleep(period_ms: usize) {
+    sys_sleep(period_ms);
+}
+pub fn nanosleep(req: &TimeSpec) -> isize {
+    sys_nanosleep(req)
+}